@@ -0,0 +1,108 @@
+//! Measures the speedup of scanning with several threads.
+//!
+//! Runs `find-git-repositories` on a generated tree with an increasing number of `--jobs`, checks
+//! that the output does not change, and reports the median time of each run. Pass a directory to
+//! scan it instead of the generated tree, e.g. a network home directory, where the speedup is
+//! highest since threads overlap the round-trips to the server:
+//!
+//!     cargo bench -p find-git-repositories -- ~/src
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// The number of subdirectories of each generated directory.
+const FANOUT: usize = 10;
+/// The depth of the generated tree, i.e. about `FANOUT.pow(DEPTH)` directories.
+const DEPTH: usize = 4;
+/// The number of timed runs for each number of threads.
+const RUNS: usize = 5;
+
+fn main() {
+    // Cargo passes `--bench` to benchmarks without a harness.
+    let root = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let generated = root.is_none();
+    let root = match root {
+        Some(root) => PathBuf::from(root),
+        None => {
+            let root =
+                std::env::temp_dir().join(format!("find-git-repositories-{}", std::process::id()));
+            let start = Instant::now();
+            let dirs = generate(&root, DEPTH);
+            println!("generated {dirs} directories in {:?}", start.elapsed());
+            root
+        }
+    };
+
+    let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut jobs = vec![1];
+    while jobs.last() < Some(&(cpus * 4).max(8)) {
+        jobs.push(jobs.last().unwrap() * 2);
+    }
+
+    let (baseline_output, baseline) = bench(&root, 1);
+    println!("--jobs {:>3}: {baseline:>10.2?}", 1);
+    for jobs in jobs.into_iter().skip(1) {
+        let (output, median) = bench(&root, jobs);
+        assert!(
+            output == baseline_output,
+            "--jobs {jobs} changed the output"
+        );
+        println!(
+            "--jobs {jobs:>3}: {median:>10.2?} ({:.2}x)",
+            baseline.as_secs_f64() / median.as_secs_f64()
+        );
+    }
+    println!("on {cpus} logical CPUs");
+
+    if generated {
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+/// Creates a tree of `depth` levels of directories under `dir`, with a repository or a project in
+/// some of the leaves and files in every directory. Returns the number of directories created.
+fn generate(dir: &Path, depth: usize) -> usize {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("README.md"), "").unwrap();
+    fs::write(dir.join("notes.txt"), "").unwrap();
+    if depth == 0 {
+        match dir
+            .to_string_lossy()
+            .bytes()
+            .map(usize::from)
+            .sum::<usize>()
+            % 4
+        {
+            0 => fs::create_dir(dir.join(".git")).unwrap(),
+            1 => fs::write(dir.join("Cargo.toml"), "").unwrap(),
+            _ => (),
+        }
+        return 1;
+    }
+
+    1 + (0..FANOUT)
+        .map(|index| generate(&dir.join(format!("dir-{index}")), depth - 1))
+        .sum::<usize>()
+}
+
+/// Scans `root` with `jobs` threads `RUNS` times, after a warm-up run. Returns the output and the
+/// median duration of the runs.
+fn bench(root: &Path, jobs: usize) -> (Vec<u8>, Duration) {
+    let run = || {
+        let start = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_find-git-repositories"))
+            .args(["--project-markers", "Cargo.toml", "--output", "machine"])
+            .args(["--jobs", &jobs.to_string()])
+            .arg(root)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        (output.stdout, start.elapsed())
+    };
+
+    let (output, _) = run();
+    let mut durations = (0..RUNS).map(|_| run().1).collect::<Vec<_>>();
+    durations.sort();
+    (output, durations[RUNS / 2])
+}
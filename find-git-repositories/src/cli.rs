@@ -1,11 +1,17 @@
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 use clap::{Parser, ValueEnum};
+use project_markers::{
+    DESCEND_ENV_VARIABLE, EXCLUDE_ENV_VARIABLE, FOLLOW_SYMLINKS_ENV_VARIABLE,
+    IGNORE_FILES_ENV_VARIABLE, PROJECT_MARKERS_ENV_VARIABLE,
+};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub(super) struct CommandLineArgs {
-    /// The root directory from where to start the scan.
+    /// The root directory from where to start the scan. A leading `~` stands for the home
+    /// directory.
     #[arg()]
     pub(super) root: PathBuf,
 
@@ -13,6 +19,38 @@ pub(super) struct CommandLineArgs {
     #[arg(short, long, default_value_t = usize::MAX)]
     pub(super) max_depth: usize,
 
+    /// Comma-separated list of file or directory names identifying project roots, in addition to
+    /// the built-in VCS markers, e.g. `Cargo.toml,flake.nix`. Names ending with a `/` only match
+    /// directories.
+    #[arg(short, long, env = PROJECT_MARKERS_ENV_VARIABLE, default_value = "")]
+    pub(super) project_markers: String,
+
+    /// Comma-separated list of `.gitignore`-style patterns of directories to skip, in addition to
+    /// the default skip-list (`node_modules`, `target`, …), e.g. `vendor/,archive/*/old`. Negated
+    /// patterns re-include skipped directories, e.g. `!target/`.
+    #[arg(short, long, env = EXCLUDE_ENV_VARIABLE, default_value = "")]
+    pub(super) exclude: String,
+
+    /// Also skip the directories listed in `.gitignore` and `.ignore` files.
+    #[arg(short, long, env = IGNORE_FILES_ENV_VARIABLE)]
+    pub(super) ignore_files: bool,
+
+    /// Keep inspecting the child directories of project roots to find nested repositories, and
+    /// list the linked worktrees of Git repositories, labelled `<repository>@<worktree>`.
+    #[arg(short, long, env = DESCEND_ENV_VARIABLE)]
+    pub(super) descend: bool,
+
+    /// Traverse symbolic links to directories. Projects reached through a link are labelled with
+    /// the path of the link, and reported with their real path.
+    #[arg(short = 'L', long, env = FOLLOW_SYMLINKS_ENV_VARIABLE)]
+    pub(super) follow_symlinks: bool,
+
+    /// The number of threads inspecting directories. Defaults to the number of logical CPUs.
+    /// Raising it may speed up scans of network filesystems, where most of the time is spent
+    /// waiting for the server.
+    #[arg(short, long)]
+    pub(super) jobs: Option<NonZeroUsize>,
+
     /// The format in which to print the results on the standard output.
     #[arg(short, long, value_enum, default_value_t = OutputFormat::Auto)]
     pub(super) output: OutputFormat,
@@ -34,7 +72,7 @@ pub(super) enum OutputFormat {
     /// This output mode is potentially lossy if the path contains non-unicode characters.
     Compact,
 
-    /// Prints the serialized list of [PathBuf] on the standard output.
+    /// Prints the serialized map of labels to paths and marker kinds on the standard output.
     /// This option is useful for deserializing the output from another rust program.
     Machine,
 }
@@ -7,6 +7,8 @@ use thiserror;
 pub(super) enum Error {
     #[error("An unexpected error happened. Check the logs for more information.")]
     UnexpectedError(#[from] anyhow::Error),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
     #[error("Failed to read filesystem: {0:?}")]
     FileSystemReadFailed(anyhow::Error),
     #[error("Failed to serialize or write to output stream: {0:?}")]
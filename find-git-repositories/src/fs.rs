@@ -1,10 +1,55 @@
-use super::core::Result;
+use super::core::{Error, Result};
 
-use std::collections::BTreeMap;
+use project_markers::{
+    is_linked_worktree, linked_worktrees, real_path, Entry, ExcludeScope, ExcludeSet, MarkerKind,
+    MarkerSet, VisitedDirs,
+};
+use rayon::{Scope, ThreadPoolBuilder};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 
-/// Lists all git repositories under [root]. Stop traversing at [max_depth].
+/// An entry of a directory.
+struct Child {
+    path: PathBuf,
+    /// Whether the entry is a directory, or a symbolic link to a directory when following them.
+    is_dir: bool,
+    is_symlink: bool,
+}
+
+/// A directory left to inspect, along with its depth.
+struct PendingDir {
+    path: PathBuf,
+    /// The exclusion rules applying to the directory, i.e. those of its parent.
+    scope: ExcludeScope,
+    depth: usize,
+    /// Whether the directory was reached through a symbolic link, so that its path is not its real
+    /// path.
+    via_symlink: bool,
+}
+
+/// The matches found by a scan, labelled by their path relative to the scanned root.
+type Repositories = BTreeMap<PathBuf, (PathBuf, MarkerKind)>;
+
+/// Lists all project roots under [root], along with the kind of marker that identified them. Stop
+/// traversing at [max_depth], and skip the directories matching [excludes].
+///
+/// If [descend] is set, keep traversing below project roots to find nested repositories, and list
+/// the linked worktrees of Git repositories as `<repository>@<worktree>`, wherever they live.
+/// Worktrees found while traversing are left to their repository, unless it does not list them,
+/// e.g. because it lives outside of [root] or is excluded.
+///
+/// If [follow_symlinks] is set, traverse symbolic links to directories as well. Each directory is
+/// traversed once, however many links lead to it, which also breaks cycles. Links are traversed
+/// last, so that projects reachable without links are labelled with their path. Projects reached
+/// through a link are labelled with the path of the link, and reported with their real path.
+///
+/// Directories are inspected by [jobs] threads, which steal work from each other. The result does
+/// not depend on the number of threads nor on the order in which they inspect directories: links
+/// are traversed in rounds, each link claiming its target in the order of their paths once all the
+/// directories reachable without going through the links of the round have been inspected.
 ///
 /// NOTE: There's no point for this to return an iterator and progressively output matches as they
 /// are discovered since Zellij waits for the process to complete before returning all of its
@@ -12,72 +57,357 @@ use std::path::PathBuf;
 pub(super) fn list_repositories(
     root: &PathBuf,
     max_depth: usize,
-) -> Result<BTreeMap<PathBuf, PathBuf>> {
-    let mut repositories = BTreeMap::new();
-    let mut dirs_to_walk = Vec::new();
+    markers: &MarkerSet,
+    excludes: &ExcludeSet,
+    descend: bool,
+    follow_symlinks: bool,
+    jobs: NonZeroUsize,
+) -> Result<Repositories> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(jobs.get())
+        .thread_name(|index| format!("walker-{index}"))
+        .build()
+        .map_err(|error| Error::UnexpectedError(error.into()))?;
+    let walker = Walker {
+        root,
+        max_depth,
+        markers,
+        descend,
+        visited: follow_symlinks.then(VisitedDirs::default),
+        repositories: Mutex::default(),
+        links: Mutex::default(),
+        crawled_worktrees: Mutex::default(),
+        listed_worktrees: Mutex::default(),
+    };
+    if let Some(visited) = &walker.visited {
+        visited.insert(root);
+    }
 
-    let child_dirs = get_child_directories(root)?;
-    if !child_dirs.is_empty() {
-        dirs_to_walk.push((root.clone(), child_dirs, /* depth */ 1));
+    let children = get_children(root, follow_symlinks)?;
+    let root = PendingDir {
+        path: root.clone(),
+        scope: excludes.scope(root),
+        depth: 1,
+        via_symlink: false,
+    };
+    let walker = &walker;
+    pool.scope(|scope| walker.inspect(scope, root, children));
+    loop {
+        let mut links = std::mem::take(&mut *lock(&walker.links));
+        if links.is_empty() {
+            break;
+        }
+        // Claim every target before traversing any: otherwise the traversal of a link could claim
+        // the target of a later link, or a directory within it, depending on the timing of threads.
+        links.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        links.retain(|link| walker.claim(&link.path));
+        pool.scope(|scope| {
+            for link in links {
+                scope.spawn(move |scope| walker.visit(scope, link));
+            }
+        });
     }
 
-    'outer: while let Some((parent, children, depth)) = dirs_to_walk.pop() {
-        assert!(
-            children.iter().all(|e| fs::metadata(e)
-                .map(|e| e.file_type().is_dir())
-                .unwrap_or(false)),
-            "internal error: all children must be valid directories"
-        );
-
-        for dir in &children {
-            if dir
-                .file_name()
-                .map(|fname| fname == ".git")
-                .unwrap_or(false)
-            {
-                repositories.insert(
-                    parent
-                        .strip_prefix(root)
-                        .expect("`parent` is built from `root`")
-                        .to_path_buf(),
-                    parent,
+    let mut repositories = std::mem::take(&mut *lock(&walker.repositories));
+    let listed_worktrees = std::mem::take(&mut *lock(&walker.listed_worktrees));
+    for (label, worktree) in std::mem::take(&mut *lock(&walker.crawled_worktrees)) {
+        let (path, _) = &worktree;
+        if !listed_worktrees.contains(&real_path(path).unwrap_or_else(|_| path.clone())) {
+            repositories.insert(label, worktree);
+        }
+    }
+    Ok(repositories)
+}
+
+/// The state of a scan shared by the threads inspecting directories.
+struct Walker<'a> {
+    root: &'a Path,
+    max_depth: usize,
+    markers: &'a MarkerSet,
+    descend: bool,
+    /// The directories visited so far, if following symbolic links.
+    visited: Option<VisitedDirs>,
+    repositories: Mutex<Repositories>,
+    /// The symbolic links to directories left to traverse in the next round.
+    links: Mutex<Vec<PendingDir>>,
+    /// The linked worktrees found while traversing, left to their repository if it lists them.
+    crawled_worktrees: Mutex<Repositories>,
+    /// The real path of the linked worktrees listed by their repository.
+    listed_worktrees: Mutex<HashSet<PathBuf>>,
+}
+
+impl Walker<'_> {
+    /// Lists the entries of [dir] and inspects it. Unreadable directories are skipped.
+    fn visit<'s>(&'s self, scope: &Scope<'s>, dir: PendingDir) {
+        // An error occured while traversing directories. Ignore it and continue.
+        if let Ok(children) = get_children(&dir.path, self.visited.is_some()) {
+            self.inspect(scope, dir, children);
+        }
+    }
+
+    /// Records [dir] if it is a project root, and spawns the inspection of its [children].
+    fn inspect<'s>(&'s self, scope: &Scope<'s>, dir: PendingDir, children: Vec<Child>) {
+        let PendingDir {
+            path: parent,
+            scope: excludes,
+            depth,
+            via_symlink,
+        } = dir;
+        let entries = children
+            .iter()
+            .filter_map(|child| {
+                Some(Entry {
+                    name: child.path.file_name()?,
+                    is_dir: child.is_dir,
+                })
+            })
+            .collect::<Vec<_>>();
+        let marker = self.markers.find(&entries);
+        if let Some(marker) = marker {
+            let label = parent
+                .strip_prefix(self.root)
+                .expect("`parent` is built from `root`")
+                .to_path_buf();
+            let path = match via_symlink {
+                true => real_path(&parent).unwrap_or_else(|_| parent.clone()),
+                false => parent.clone(),
+            };
+            if !self.descend {
+                lock(&self.repositories).insert(label, (path, marker));
+                return;
+            }
+            if is_linked_worktree(&parent, &entries) {
+                lock(&self.crawled_worktrees).insert(label, (path, marker));
+            } else {
+                let worktrees = linked_worktrees_of(&label, &parent, marker).collect::<Vec<_>>();
+                lock(&self.listed_worktrees).extend(
+                    worktrees
+                        .iter()
+                        .map(|(_, (path, _))| real_path(path).unwrap_or_else(|_| path.clone())),
                 );
-                continue 'outer;
+                let mut repositories = lock(&self.repositories);
+                repositories.extend(worktrees);
+                repositories.insert(label, (path, marker));
+            }
+            if marker == MarkerKind::GitBare {
+                // The repository is a Git directory itself.
+                return;
             }
         }
-        if depth < max_depth {
-            for dir in children {
-                let Ok(child_dirs) = get_child_directories(&dir) else {
-                    // An error occured while traversing directories. Ignore it and continue.
-                    continue;
-                };
-                if !child_dirs.is_empty() {
-                    dirs_to_walk.push((dir, child_dirs, depth + 1));
-                }
+        if depth >= self.max_depth {
+            return;
+        }
+
+        let excludes = excludes.enter(&parent, &entries);
+        for child in children.into_iter().filter(|child| child.is_dir) {
+            let entry = Entry {
+                name: child.path.file_name().unwrap_or_default(),
+                is_dir: true,
+            };
+            if excludes.is_excluded(&child.path)
+                || (marker.is_some() && self.markers.is_vcs_metadata(&entry))
+            {
+                continue;
+            }
+            let pending = PendingDir {
+                path: child.path,
+                scope: excludes.clone(),
+                depth: depth + 1,
+                via_symlink: via_symlink || child.is_symlink,
+            };
+            if child.is_symlink {
+                lock(&self.links).push(pending);
+            } else if self.claim(&pending.path) {
+                scope.spawn(move |scope| self.visit(scope, pending));
             }
         }
     }
 
-    Ok(repositories)
+    /// Whether the directory at [path] is still to be inspected, i.e. it was not reached yet
+    /// through another path.
+    fn claim(&self, path: &Path) -> bool {
+        self.visited
+            .as_ref()
+            .is_none_or(|visited| visited.insert(path))
+    }
+}
+
+/// Locks [mutex], ignoring poisoning: a panicking thread aborts the scan anyway.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Lists the linked worktrees of the repository at `path`, labelled `label`, as matches.
+/// Worktrees whose directory no longer exists are skipped.
+fn linked_worktrees_of(
+    label: &Path,
+    path: &Path,
+    marker: MarkerKind,
+) -> impl Iterator<Item = (PathBuf, (PathBuf, MarkerKind))> {
+    let label = label.as_os_str().to_owned();
+    linked_worktrees(path, marker)
+        .into_iter()
+        .filter(|worktree| worktree.path.is_dir())
+        .map(move |worktree| {
+            let mut worktree_label = label.clone();
+            worktree_label.push("@");
+            worktree_label.push(&worktree.name);
+            (
+                worktree_label.into(),
+                (worktree.path, MarkerKind::GitWorktree),
+            )
+        })
 }
 
-fn get_child_directories(path: &PathBuf) -> Result<Vec<PathBuf>> {
+/// Lists the entries of the directory at `path`. Symbolic links to directories count as
+/// directories if [follow_symlinks] is set.
+fn get_children(path: &PathBuf, follow_symlinks: bool) -> Result<Vec<Child>> {
     let mut children = Vec::new();
 
     let entries = match fs::read_dir(path) {
         Ok(entries) => entries,
-        Err(error) => return Err(crate::core::Error::FileSystemReadFailed(error.into())),
+        Err(error) => return Err(Error::FileSystemReadFailed(error.into())),
     };
 
     for entry in entries {
         if let Ok(entry) = entry {
             if let Ok(ft) = entry.file_type() {
-                if ft.is_dir() {
-                    children.push(entry.path());
-                }
+                let path = entry.path();
+                let is_dir = match ft.is_symlink() && follow_symlinks {
+                    true => fs::metadata(&path).is_ok_and(|metadata| metadata.is_dir()),
+                    false => ft.is_dir(),
+                };
+                children.push(Child {
+                    path,
+                    is_dir,
+                    is_symlink: ft.is_symlink(),
+                });
             }
         }
     }
 
     Ok(children)
 }
+
+#[cfg(unix)]
+#[test]
+fn output_does_not_depend_on_jobs() {
+    use std::os::unix::fs::symlink;
+
+    let tmp = std::env::temp_dir().join(format!("walker-{}", std::process::id()));
+    let root = tmp.join("root");
+    for index in 0..32 {
+        fs::create_dir_all(root.join(format!("src/{index}/app/.git"))).unwrap();
+    }
+    fs::create_dir_all(root.join("lib/.hg")).unwrap();
+    fs::create_dir_all(tmp.join("elsewhere/app/.jj")).unwrap();
+    // Both links lead outside of the root, the first one in the order of paths wins.
+    symlink("../elsewhere", root.join("link")).unwrap();
+    symlink("../../elsewhere", root.join("src/link")).unwrap();
+    // Links to directories reachable without links, or to ancestors, are skipped.
+    symlink("lib", root.join("lib-link")).unwrap();
+    symlink("..", root.join("src/0/up")).unwrap();
+    // Links into the target of another link of the same round keep their own target.
+    fs::create_dir(root.join("z")).unwrap();
+    for index in 0..400 {
+        fs::create_dir_all(tmp.join(format!("nested/{index}/sub/.git"))).unwrap();
+        symlink(
+            format!("../nested/{index}"),
+            root.join(format!("z/{index}")),
+        )
+        .unwrap();
+    }
+    symlink("../nested", root.join("nested")).unwrap();
+    let scan = |jobs| {
+        list_repositories(
+            &root,
+            usize::MAX,
+            &MarkerSet::default(),
+            &ExcludeSet::default(),
+            false,
+            true,
+            NonZeroUsize::new(jobs).unwrap(),
+        )
+        .unwrap()
+    };
+
+    let repositories = scan(1);
+    assert_eq!(repositories.len(), 434);
+    assert_eq!(
+        repositories.get(Path::new("link/app")),
+        Some(&(tmp.join("elsewhere/app"), MarkerKind::Jujutsu))
+    );
+    assert!(repositories.contains_key(Path::new("lib")));
+    for index in 0..400 {
+        assert!(repositories.contains_key(&PathBuf::from(format!("z/{index}/sub"))));
+    }
+    for jobs in [2, 8, 16] {
+        for _ in 0..4 {
+            assert_eq!(scan(jobs), repositories);
+        }
+    }
+
+    fs::remove_dir_all(&tmp).unwrap();
+}
+
+#[test]
+fn worktrees_are_left_to_listed_repositories() {
+    let tmp = std::env::temp_dir().join(format!("worktrees-{}", std::process::id()));
+    let root = tmp.join("root");
+    // `main` lists `feat`, while `fix` belongs to a repository outside of the root.
+    for (repository, worktree) in [(root.join("main"), "feat"), (tmp.join("outside"), "fix")] {
+        let admin_dir = repository.join(".git/worktrees").join(worktree);
+        fs::create_dir_all(&admin_dir).unwrap();
+        fs::create_dir_all(root.join(worktree)).unwrap();
+        fs::write(
+            admin_dir.join("gitdir"),
+            format!("{}\n", root.join(worktree).join(".git").display()),
+        )
+        .unwrap();
+        fs::write(
+            root.join(worktree).join(".git"),
+            format!("gitdir: {}\n", admin_dir.display()),
+        )
+        .unwrap();
+    }
+    let scan = |descend| {
+        list_repositories(
+            &root,
+            usize::MAX,
+            &MarkerSet::default(),
+            &ExcludeSet::default(),
+            descend,
+            false,
+            NonZeroUsize::MIN,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(label, (path, marker))| (label.to_string_lossy().into_owned(), path, marker))
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        scan(false),
+        vec![
+            ("feat".into(), root.join("feat"), MarkerKind::Git),
+            ("fix".into(), root.join("fix"), MarkerKind::Git),
+            ("main".into(), root.join("main"), MarkerKind::Git),
+        ]
+    );
+    assert_eq!(
+        scan(true),
+        vec![
+            ("fix".into(), root.join("fix"), MarkerKind::Git),
+            ("main".into(), root.join("main"), MarkerKind::Git),
+            (
+                "main@feat".into(),
+                root.join("feat"),
+                MarkerKind::GitWorktree
+            ),
+        ]
+    );
+
+    fs::remove_dir_all(&tmp).unwrap();
+}
@@ -1,6 +1,9 @@
-use core::Result;
+use core::{Error, Result};
 use fs::list_repositories;
 use output::get_output_fn;
+use project_markers::{ExcludeSet, MarkerSet};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 mod cli;
 mod core;
@@ -8,20 +11,48 @@ mod fs;
 mod marshall;
 mod output;
 
-/// Recursively scans [cli::CommandLineArgs::root] looking for Git repositories, and prints all
+/// Recursively scans [cli::CommandLineArgs::root] looking for project roots, i.e. VCS repositories
+/// and directories containing one of [cli::CommandLineArgs::project_markers], and prints all
 /// paths to stdout according to the specified [cli::CommandLineArgs::output] format.
-/// Looks no further than [cli::CommandLineArgs::max_depth] depth.
+/// Looks no further than [cli::CommandLineArgs::max_depth] depth, and skips the directories
+/// matching [cli::CommandLineArgs::exclude] (and ignore files if [cli::CommandLineArgs::ignore_files]).
+/// Directories are inspected by [cli::CommandLineArgs::jobs] threads, without affecting the output.
 /// Note that only [cli::OutputFormat::Machine] is guaranteed to be lossless: based on the host
 /// system [std::path::PathBuf::display()] may have to substitute non-unicode characters.
 /// On the other hand [cli::OutputFormat::Machine] encodes raw [PathBuf]s, therefore preserving
 /// the original encoding.
 /// Shell expansion is not performed on [cli::CommandLineArgs::root] and thus must be performed
-/// out of band.
+/// out of band, except for a leading `~` since Zellij plugins run programs without a shell.
 fn main() -> Result<()> {
     let args = cli::CommandLineArgs::parse();
 
     let output = get_output_fn(args.output);
-    let matches = list_repositories(&args.root, args.max_depth)?;
+    let markers = MarkerSet::with_project_markers(&args.project_markers)
+        .map_err(|reason| Error::InvalidArgument(format!("--project-markers: {reason}")))?;
+    let excludes = ExcludeSet::with_patterns(&args.exclude, args.ignore_files)
+        .map_err(|reason| Error::InvalidArgument(format!("--exclude: {reason}")))?;
+    let matches = list_repositories(
+        &expand_home(args.root),
+        args.max_depth,
+        &markers,
+        &excludes,
+        args.descend,
+        args.follow_symlinks,
+        args.jobs.unwrap_or_else(default_jobs),
+    )?;
 
     output(matches)
 }
+
+/// Expands a leading `~` in [path] to the home directory, if `HOME` is set.
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(relative), Some(home)) => PathBuf::from(home).join(relative),
+        _ => path,
+    }
+}
+
+/// The number of logical CPUs, if it can be determined.
+fn default_jobs() -> NonZeroUsize {
+    std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
@@ -4,8 +4,9 @@ use std::{collections::BTreeMap, io::IsTerminal, path::PathBuf};
 use super::cli::OutputFormat;
 use super::core::{Error, Result};
 use super::marshall::serialize;
+use project_markers::MarkerKind;
 
-type OutputFn = fn(BTreeMap<PathBuf, PathBuf>) -> Result<()>;
+type OutputFn = fn(BTreeMap<PathBuf, (PathBuf, MarkerKind)>) -> Result<()>;
 
 pub(super) fn get_output_fn(format: OutputFormat) -> OutputFn {
     match format {
@@ -24,9 +25,14 @@ pub(super) fn get_output_fn(format: OutputFormat) -> OutputFn {
 
 /// Prints the paths to the standard output, one per line.
 /// This is a human-friendly output format.
-fn output_display(paths: BTreeMap<PathBuf, PathBuf>) -> Result<()> {
-    for (label, path) in paths {
-        println!("{} -> {}", label.display(), path.display());
+fn output_display(paths: BTreeMap<PathBuf, (PathBuf, MarkerKind)>) -> Result<()> {
+    for (label, (path, marker)) in paths {
+        println!(
+            "{} -> {} ({})",
+            label.display(),
+            path.display(),
+            marker.name()
+        );
     }
 
     Ok(())
@@ -34,17 +40,18 @@ fn output_display(paths: BTreeMap<PathBuf, PathBuf>) -> Result<()> {
 
 /// Prints the paths to the standard output, separated by `\0`.
 /// This is a lossy machine-friendly output format.
-fn output_compact(paths: BTreeMap<PathBuf, PathBuf>) -> Result<()> {
-    for (label, path) in paths {
+fn output_compact(paths: BTreeMap<PathBuf, (PathBuf, MarkerKind)>) -> Result<()> {
+    for (label, (path, _)) in paths {
         print!("{}\0{}\0", label.display(), path.display());
     }
 
     Ok(())
 }
 
-/// Prints the paths to the standard output, serialized into the RMP format.
+/// Prints the paths and the kind of marker that identified them to the standard output, serialized
+/// into the RMP format.
 /// This is a lossless machine-friendly output format.
-fn output_machine(paths: BTreeMap<PathBuf, PathBuf>) -> Result<()> {
+fn output_machine(paths: BTreeMap<PathBuf, (PathBuf, MarkerKind)>) -> Result<()> {
     let buf = serialize(&paths)?;
 
     if let Err(error) = std::io::stdout().write_all(&buf.as_ref()) {
@@ -0,0 +1,244 @@
+/// Exclusion of directories from scans, e.g. dependency caches that never contain projects of
+/// their own but take ages to crawl.
+use crate::Entry;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The environment variable through which the plugin passes its configured exclude patterns to
+/// external programs, in the format expected by [ExcludeSet::with_patterns].
+pub const EXCLUDE_ENV_VARIABLE: &str = "PRIME_HOPPER_EXCLUDE";
+
+/// The environment variable through which the plugin tells external programs whether to honour
+/// ignore files. Either `true` or `false`.
+pub const IGNORE_FILES_ENV_VARIABLE: &str = "PRIME_HOPPER_IGNORE_FILES";
+
+/// The directories skipped unless re-included by a negated pattern, e.g. `!target/`.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "node_modules/",
+    "target/",
+    ".direnv/",
+    ".venv/",
+    "__pycache__/",
+    ".cache/",
+    "**/nix/store/",
+];
+
+/// The files listing the directories to skip when [ExcludeSet::ignore_files] is set, in order of
+/// precedence.
+const IGNORE_FILE_NAMES: [&str; 2] = [".ignore", ".gitignore"];
+
+/// The directories to skip while scanning.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExcludeSet {
+    /// The patterns added to [DEFAULT_EXCLUDES].
+    patterns: Vec<String>,
+    /// Whether to also skip the directories listed in `.gitignore` and `.ignore` files.
+    ignore_files: bool,
+}
+
+impl ExcludeSet {
+    /// The [DEFAULT_EXCLUDES], followed by the patterns listed in `spec`.
+    ///
+    /// `spec` is a comma-separated list of patterns in `.gitignore` syntax, relative to the scanned
+    /// root, e.g. `vendor/,archive/*/old`. Later patterns take precedence, so that `!target/`
+    /// re-includes `target` directories.
+    pub fn with_patterns(spec: &str, ignore_files: bool) -> Result<Self, String> {
+        let patterns = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut builder = GitignoreBuilder::new("");
+        for pattern in &patterns {
+            builder
+                .add_line(None, pattern)
+                .map_err(|error| format!("invalid exclude pattern {pattern:?}: {error}"))?;
+        }
+
+        Ok(Self {
+            patterns,
+            ignore_files,
+        })
+    }
+
+    /// The patterns added to the [DEFAULT_EXCLUDES], in the format expected by
+    /// [ExcludeSet::with_patterns].
+    pub fn patterns_spec(&self) -> String {
+        self.patterns.join(",")
+    }
+
+    pub fn ignore_files(&self) -> bool {
+        self.ignore_files
+    }
+
+    /// The rules applying to the directories under `root`. Invalid patterns are skipped.
+    pub fn scope(&self, root: &Path) -> ExcludeScope {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in DEFAULT_EXCLUDES
+            .iter()
+            .copied()
+            .chain(self.patterns.iter().map(String::as_str))
+        {
+            _ = builder.add_line(None, pattern);
+        }
+
+        ExcludeScope {
+            patterns: Arc::new(builder.build().unwrap_or_else(|_| Gitignore::empty())),
+            ignore_files: self.ignore_files,
+            ignores: None,
+        }
+    }
+}
+
+/// The rules applying to the children of a directory: the patterns of the [ExcludeSet], and the
+/// ignore files found in the directory and its ancestors up to the scanned root.
+#[derive(Clone, Debug)]
+pub struct ExcludeScope {
+    patterns: Arc<Gitignore>,
+    ignore_files: bool,
+    /// The ignore files of the innermost directory that has any, linked to those of its ancestors.
+    ignores: Option<Arc<IgnoreFiles>>,
+}
+
+#[derive(Debug)]
+struct IgnoreFiles {
+    matcher: Gitignore,
+    parent: Option<Arc<IgnoreFiles>>,
+}
+
+impl ExcludeScope {
+    /// The scope of the children of `dir`, whose `entries` may include ignore files.
+    pub fn enter(&self, dir: &Path, entries: &[Entry]) -> Self {
+        if !self.ignore_files {
+            return self.clone();
+        }
+        let ignore_files = IGNORE_FILE_NAMES
+            .iter()
+            .filter(|name| {
+                entries
+                    .iter()
+                    .any(|entry| !entry.is_dir && entry.name == **name)
+            })
+            .map(|name| dir.join(name))
+            .collect::<Vec<PathBuf>>();
+        if ignore_files.is_empty() {
+            return self.clone();
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        // Files read last take precedence.
+        for path in ignore_files.iter().rev() {
+            // Keep the valid lines of partially invalid files.
+            _ = builder.add(path);
+        }
+        let Ok(matcher) = builder.build() else {
+            return self.clone();
+        };
+
+        Self {
+            ignores: Some(Arc::new(IgnoreFiles {
+                matcher,
+                parent: self.ignores.clone(),
+            })),
+            ..self.clone()
+        }
+    }
+
+    /// Whether to skip the directory at `dir`, a child of the directory of this scope. The
+    /// patterns of the [ExcludeSet] take precedence over ignore files, and ignore files over those
+    /// of their ancestors.
+    pub fn is_excluded(&self, dir: &Path) -> bool {
+        match self.patterns.matched(dir, true) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => (),
+        }
+        let mut ignores = self.ignores.as_deref();
+        while let Some(IgnoreFiles { matcher, parent }) = ignores {
+            match matcher.matched(dir, true) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => ignores = parent.as_deref(),
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::fs;
+
+    #[test]
+    fn default_excludes() {
+        let scope = ExcludeSet::default().scope(Path::new("/src"));
+
+        assert!(scope.is_excluded(Path::new("/src/web/node_modules")));
+        assert!(scope.is_excluded(Path::new("/src/target")));
+        assert!(scope.is_excluded(Path::new("/src/nix/store")));
+        assert!(!scope.is_excluded(Path::new("/src/web")));
+        assert!(!scope.is_excluded(Path::new("/src/targets")));
+    }
+
+    #[test]
+    fn configured_patterns_take_precedence() {
+        let excludes = ExcludeSet::with_patterns("archive/, !target/, *.bak", false).unwrap();
+        let scope = excludes.scope(Path::new("/src"));
+
+        assert!(scope.is_excluded(Path::new("/src/archive")));
+        assert!(scope.is_excluded(Path::new("/src/work/archive")));
+        assert!(scope.is_excluded(Path::new("/src/api.bak")));
+        assert!(!scope.is_excluded(Path::new("/src/target")));
+        assert_eq!(excludes.patterns_spec(), "archive/,!target/,*.bak");
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(ExcludeSet::with_patterns("archive/{old", false).is_err());
+        assert_eq!(
+            ExcludeSet::with_patterns(" , ", false),
+            Ok(ExcludeSet::default())
+        );
+    }
+
+    #[test]
+    fn ignore_files_apply_to_descendants() {
+        let root = std::env::temp_dir().join(format!("exclude-{}", std::process::id()));
+        let work = root.join("work");
+        fs::create_dir_all(&work).unwrap();
+        fs::write(root.join(".gitignore"), "scratch/\nbuild/\n").unwrap();
+        fs::write(work.join(".ignore"), "!build/\n").unwrap();
+        let entries = |name| {
+            vec![Entry {
+                name: OsStr::new(name),
+                is_dir: false,
+            }]
+        };
+
+        let scope = ExcludeSet::with_patterns("", true)
+            .unwrap()
+            .scope(&root)
+            .enter(&root, &entries(".gitignore"));
+        assert!(scope.is_excluded(&root.join("scratch")));
+        assert!(scope.is_excluded(&root.join("build")));
+
+        let work_scope = scope.enter(&work, &entries(".ignore"));
+        assert!(work_scope.is_excluded(&work.join("scratch")));
+        assert!(!work_scope.is_excluded(&work.join("build")));
+
+        let scope = ExcludeSet::default()
+            .scope(&root)
+            .enter(&root, &entries(".gitignore"));
+        assert!(!scope.is_excluded(&root.join("scratch")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
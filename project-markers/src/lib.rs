@@ -0,0 +1,279 @@
+/// Recognition of project roots, shared by the plugin's file system worker and the
+/// `find-git-repositories` program.
+///
+/// A directory is a project root if it contains one of the entries of a [MarkerSet], e.g. a `.git`
+/// directory. Crawlers stop descending into a directory once it is recognised as a project root,
+/// or once it is excluded by an [ExcludeSet], unless asked to descend into repositories to find
+/// nested ones.
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+
+mod exclude;
+mod symlinks;
+mod worktrees;
+
+pub use exclude::{
+    ExcludeScope, ExcludeSet, DEFAULT_EXCLUDES, EXCLUDE_ENV_VARIABLE, IGNORE_FILES_ENV_VARIABLE,
+};
+pub use symlinks::{real_path, VisitedDirs};
+pub use worktrees::{is_linked_worktree, linked_worktrees, Worktree};
+
+/// The environment variable through which the plugin passes its configured project markers to
+/// external programs, in the format expected by [MarkerSet::with_project_markers].
+pub const PROJECT_MARKERS_ENV_VARIABLE: &str = "PRIME_HOPPER_PROJECT_MARKERS";
+
+/// The environment variable through which the plugin tells external programs whether to descend
+/// into repositories. Either `true` or `false`.
+pub const DESCEND_ENV_VARIABLE: &str = "PRIME_HOPPER_DESCEND_INTO_REPOSITORIES";
+
+/// The environment variable through which the plugin tells external programs whether to follow
+/// symbolic links. Either `true` or `false`.
+pub const FOLLOW_SYMLINKS_ENV_VARIABLE: &str = "PRIME_HOPPER_FOLLOW_SYMLINKS";
+
+/// The kind of project a directory is recognised as, by the marker found in it.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum MarkerKind {
+    /// A Git repository or worktree, including submodules, whose `.git` is a file.
+    Git,
+    /// A bare Git repository, i.e. a Git directory without a work tree.
+    GitBare,
+    /// A linked worktree of a Git repository, listed along with that repository.
+    GitWorktree,
+    Jujutsu,
+    Mercurial,
+    Sapling,
+    Pijul,
+    /// A directory containing one of the configured project markers.
+    Project,
+}
+
+impl MarkerKind {
+    /// A short name for the kind, as displayed by `find-git-repositories`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarkerKind::Git => "git",
+            MarkerKind::GitBare => "git-bare",
+            MarkerKind::GitWorktree => "git-worktree",
+            MarkerKind::Jujutsu => "jj",
+            MarkerKind::Mercurial => "hg",
+            MarkerKind::Sapling => "sapling",
+            MarkerKind::Pijul => "pijul",
+            MarkerKind::Project => "project",
+        }
+    }
+}
+
+/// The type of entry a marker matches.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum EntryType {
+    Directory,
+    /// Either a directory or a file.
+    Any,
+}
+
+/// An entry whose presence in a directory identifies it as a project root.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Marker {
+    pub name: String,
+    pub entry_type: EntryType,
+    pub kind: MarkerKind,
+}
+
+impl Marker {
+    fn new(name: &str, entry_type: EntryType, kind: MarkerKind) -> Self {
+        Self {
+            name: name.to_string(),
+            entry_type,
+            kind,
+        }
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        entry.name == OsStr::new(&self.name) && (entry.is_dir || self.entry_type == EntryType::Any)
+    }
+}
+
+/// An entry of the directory being inspected by [MarkerSet::find].
+pub struct Entry<'a> {
+    pub name: &'a OsStr,
+    pub is_dir: bool,
+}
+
+/// The markers identifying project roots, in order of precedence.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MarkerSet {
+    markers: Vec<Marker>,
+}
+
+impl Default for MarkerSet {
+    /// The built-in VCS markers. Jujutsu and Sapling repositories may be colocated with a Git
+    /// repository, and take precedence over it.
+    fn default() -> Self {
+        Self {
+            markers: vec![
+                Marker::new(".jj", EntryType::Directory, MarkerKind::Jujutsu),
+                Marker::new(".sl", EntryType::Directory, MarkerKind::Sapling),
+                // Worktrees and submodules have a `.git` file pointing to the actual Git directory.
+                Marker::new(".git", EntryType::Any, MarkerKind::Git),
+                Marker::new(".hg", EntryType::Directory, MarkerKind::Mercurial),
+                Marker::new(".pijul", EntryType::Directory, MarkerKind::Pijul),
+            ],
+        }
+    }
+}
+
+impl MarkerSet {
+    /// The built-in VCS markers, followed by the project markers listed in `spec`.
+    ///
+    /// `spec` is a comma-separated list of file or directory names, e.g.
+    /// `Cargo.toml,flake.nix,.project-root`. Names ending with a `/` only match directories.
+    pub fn with_project_markers(spec: &str) -> Result<Self, String> {
+        let mut markers = Self::default();
+        for name in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let (name, entry_type) = match name.strip_suffix('/') {
+                Some(name) => (name, EntryType::Directory),
+                None => (name, EntryType::Any),
+            };
+            if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+                return Err(format!("invalid marker name: {name:?}"));
+            }
+            markers
+                .markers
+                .push(Marker::new(name, entry_type, MarkerKind::Project));
+        }
+
+        Ok(markers)
+    }
+
+    /// The project markers added to the built-in VCS markers, in the format expected by
+    /// [MarkerSet::with_project_markers].
+    pub fn project_markers_spec(&self) -> String {
+        self.markers
+            .iter()
+            .filter(|marker| marker.kind == MarkerKind::Project)
+            .map(|marker| match marker.entry_type {
+                EntryType::Directory => format!("{}/", marker.name),
+                EntryType::Any => marker.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Whether `entry` holds the metadata of a VCS, e.g. a `.git` directory, which crawlers
+    /// descending into repositories must skip.
+    pub fn is_vcs_metadata(&self, entry: &Entry) -> bool {
+        entry.is_dir
+            && self
+                .markers
+                .iter()
+                .any(|marker| marker.kind != MarkerKind::Project && marker.matches(entry))
+    }
+
+    /// Returns the kind of the first marker found among the `entries` of a directory, if any.
+    pub fn find(&self, entries: &[Entry]) -> Option<MarkerKind> {
+        self.markers
+            .iter()
+            .find(|marker| entries.iter().any(|entry| marker.matches(entry)))
+            .map(|marker| marker.kind)
+            .or_else(|| is_bare_git_repository(entries).then_some(MarkerKind::GitBare))
+    }
+}
+
+/// Whether `entries` are those of a bare Git repository, which has no `.git` directory since it
+/// is a Git directory itself.
+fn is_bare_git_repository(entries: &[Entry]) -> bool {
+    let has = |name: &str, is_dir: bool| {
+        entries
+            .iter()
+            .any(|entry| entry.name == OsStr::new(name) && entry.is_dir == is_dir)
+    };
+    has("HEAD", false) && has("objects", true) && has("refs", true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(markers: &MarkerSet, entries: &[(&str, bool)]) -> Option<MarkerKind> {
+        let entries = entries
+            .iter()
+            .map(|&(name, is_dir)| Entry {
+                name: OsStr::new(name),
+                is_dir,
+            })
+            .collect::<Vec<_>>();
+        markers.find(&entries)
+    }
+
+    #[test]
+    fn find_vcs_markers() {
+        let markers = MarkerSet::default();
+
+        assert_eq!(find(&markers, &[(".git", true)]), Some(MarkerKind::Git));
+        // Worktrees and submodules.
+        assert_eq!(find(&markers, &[(".git", false)]), Some(MarkerKind::Git));
+        assert_eq!(
+            find(&markers, &[(".git", true), (".jj", true)]),
+            Some(MarkerKind::Jujutsu)
+        );
+        assert_eq!(
+            find(&markers, &[(".hg", true)]),
+            Some(MarkerKind::Mercurial)
+        );
+        assert_eq!(find(&markers, &[(".hg", false)]), None);
+        assert_eq!(
+            find(
+                &markers,
+                &[("HEAD", false), ("objects", true), ("refs", true)]
+            ),
+            Some(MarkerKind::GitBare)
+        );
+        assert_eq!(find(&markers, &[("HEAD", false), ("objects", true)]), None);
+        assert_eq!(find(&markers, &[("Cargo.toml", false)]), None);
+
+        let entry = |name| Entry {
+            name: OsStr::new(name),
+            is_dir: true,
+        };
+        assert!(markers.is_vcs_metadata(&entry(".git")));
+        assert!(!markers.is_vcs_metadata(&entry("src")));
+    }
+
+    #[test]
+    fn find_project_markers() {
+        let markers = MarkerSet::with_project_markers("Cargo.toml, .project-root,build/").unwrap();
+
+        assert_eq!(
+            find(&markers, &[("Cargo.toml", false)]),
+            Some(MarkerKind::Project)
+        );
+        assert_eq!(
+            find(&markers, &[("Cargo.toml", false), (".git", true)]),
+            Some(MarkerKind::Git)
+        );
+        assert_eq!(
+            find(&markers, &[("build", true)]),
+            Some(MarkerKind::Project)
+        );
+        assert_eq!(find(&markers, &[("build", false)]), None);
+        assert_eq!(
+            markers.project_markers_spec(),
+            "Cargo.toml,.project-root,build/"
+        );
+    }
+
+    #[test]
+    fn invalid_project_markers() {
+        assert!(MarkerSet::with_project_markers("src/Cargo.toml").is_err());
+        assert!(MarkerSet::with_project_markers("..").is_err());
+        assert!(MarkerSet::with_project_markers("/").is_err());
+        assert_eq!(
+            MarkerSet::with_project_markers(""),
+            Ok(MarkerSet::default())
+        );
+    }
+}
@@ -0,0 +1,131 @@
+/// Bookkeeping for crawlers following symbolic links, which may lead to the same directory through
+/// several paths, or back to one of its ancestors.
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What identifies a directory regardless of the path it is reached through: its device and inode
+/// numbers where available, and its real path otherwise (e.g. on WASI, where they are not exposed).
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    real_path(path)
+}
+
+/// The directories visited by a crawl, possibly shared by several threads.
+#[derive(Debug, Default)]
+pub struct VisitedDirs {
+    visited: Mutex<HashSet<DirId>>,
+}
+
+impl VisitedDirs {
+    /// Records a visit of the directory at `path`. Returns `false` if it was already visited
+    /// through another path, including when `path` leads back to one of its ancestors, or if it
+    /// cannot be inspected.
+    pub fn insert(&self, path: &Path) -> bool {
+        // Inspect the directory before locking, as it may be slow on network filesystems.
+        match dir_id(path) {
+            Ok(id) => self
+                .visited
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(id),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Returns the absolute path of `path` with all symbolic links resolved.
+#[cfg(not(target_os = "wasi"))]
+pub fn real_path(path: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(path)
+}
+
+/// Returns the absolute path of `path` with all symbolic links resolved.
+///
+/// WASI does not support [fs::canonicalize], so links are resolved one component at a time.
+#[cfg(target_os = "wasi")]
+pub fn real_path(path: &Path) -> io::Result<PathBuf> {
+    use std::path::Component;
+
+    /// The maximum number of symbolic links resolved in a single path, as in Linux.
+    const MAX_SYMLINK_HOPS: usize = 40;
+
+    fn components(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+        path.components()
+            .rev()
+            .map(|component| PathBuf::from(component.as_os_str()))
+    }
+
+    let mut resolved = PathBuf::from("/");
+    // The components left to resolve, in reverse order.
+    let mut pending = components(path).collect::<Vec<_>>();
+    let mut hops = 0;
+    while let Some(component) = pending.pop() {
+        match component.components().next() {
+            Some(Component::RootDir | Component::Prefix(_)) => resolved = PathBuf::from("/"),
+            Some(Component::ParentDir) => _ = resolved.pop(),
+            Some(Component::Normal(name)) => {
+                resolved.push(name);
+                if !fs::symlink_metadata(&resolved)?.is_symlink() {
+                    continue;
+                }
+                hops += 1;
+                if hops > MAX_SYMLINK_HOPS {
+                    return Err(io::Error::other("too many levels of symbolic links"));
+                }
+                let target = fs::read_link(&resolved)?;
+                resolved.pop();
+                pending.extend(components(&target));
+            }
+            Some(Component::CurDir) | None => (),
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn visits_are_deduplicated_across_links() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("symlinks-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/app")).unwrap();
+        symlink("src/app", root.join("app")).unwrap();
+        // A cycle.
+        symlink("..", root.join("src/app/up")).unwrap();
+
+        let visited = VisitedDirs::default();
+        assert!(visited.insert(&root));
+        assert!(visited.insert(&root.join("src/app")));
+        assert!(!visited.insert(&root.join("app")));
+        assert!(visited.insert(&root.join("src")));
+        assert!(!visited.insert(&root.join("src/app/up")));
+        assert!(!visited.insert(&root.join("missing")));
+
+        assert_eq!(
+            real_path(&root.join("app/up/app")).unwrap(),
+            real_path(&root.join("src/app")).unwrap()
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
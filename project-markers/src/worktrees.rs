@@ -0,0 +1,131 @@
+/// Discovery of the linked worktrees of Git repositories, which may live anywhere on the
+/// filesystem and are therefore listed along with their repository rather than found by crawling.
+use crate::{Entry, MarkerKind};
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A worktree added to a repository with `git worktree add`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Worktree {
+    /// The name of the worktree in the repository, usually the basename of its directory.
+    pub name: OsString,
+    /// The directory of the worktree, as recorded by Git. Worktrees that were removed without
+    /// `git worktree remove` are recorded until pruned.
+    pub path: PathBuf,
+}
+
+/// Lists the linked worktrees of the repository at `dir`, recognised as `kind`.
+pub fn linked_worktrees(dir: &Path, kind: MarkerKind) -> Vec<Worktree> {
+    let git_dir = match kind {
+        MarkerKind::GitBare => dir.to_path_buf(),
+        // Jujutsu and Sapling repositories may be colocated with a Git repository.
+        _ => dir.join(".git"),
+    };
+    // A `.git` file, i.e. a worktree or submodule, has no `worktrees` of its own.
+    let Ok(admin_dirs) = fs::read_dir(git_dir.join("worktrees")) else {
+        return vec![];
+    };
+
+    let mut worktrees = admin_dirs
+        .flatten()
+        .filter_map(|admin_dir| {
+            // `gitdir` holds the path of the `.git` file of the worktree, relative to the
+            // administrative directory with `worktree.useRelativePaths`.
+            let gitdir = fs::read_to_string(admin_dir.path().join("gitdir")).ok()?;
+            let dot_git = normalize(&admin_dir.path().join(gitdir.trim()));
+            Some(Worktree {
+                name: admin_dir.file_name(),
+                path: dot_git.parent()?.to_path_buf(),
+            })
+        })
+        .collect::<Vec<_>>();
+    worktrees.sort_by(|a, b| a.name.cmp(&b.name));
+    worktrees
+}
+
+/// Whether the directory at `dir`, whose entries are `entries`, is a linked worktree of another
+/// repository, i.e. its `.git` is a file pointing to the `worktrees` directory of that repository.
+pub fn is_linked_worktree(dir: &Path, entries: &[Entry]) -> bool {
+    let has_dot_git_file = entries
+        .iter()
+        .any(|entry| !entry.is_dir && entry.name == ".git");
+    if !has_dot_git_file {
+        return false;
+    }
+    let Ok(content) = fs::read_to_string(dir.join(".git")) else {
+        return false;
+    };
+    content
+        .strip_prefix("gitdir:")
+        .map(|gitdir| Path::new(gitdir.trim()))
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        == Some(OsStr::new("worktrees"))
+}
+
+/// Lexically normalizes `path`, e.g. `/src/app/.git/worktrees/feat/../../../feat`.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if normalized.file_name().is_some() => _ = normalized.pop(),
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_and_recognise_linked_worktrees() {
+        let root = std::env::temp_dir().join(format!("worktrees-{}", std::process::id()));
+        let admin_dir = root.join("app/.git/worktrees");
+        fs::create_dir_all(admin_dir.join("feat")).unwrap();
+        fs::create_dir_all(admin_dir.join("fix")).unwrap();
+        fs::create_dir_all(root.join("feat")).unwrap();
+        fs::write(admin_dir.join("feat/gitdir"), "../../../../feat/.git\n").unwrap();
+        fs::write(admin_dir.join("fix/gitdir"), "/elsewhere/fix/.git\n").unwrap();
+        fs::write(
+            root.join("feat/.git"),
+            format!("gitdir: {}\n", admin_dir.join("feat").display()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            linked_worktrees(&root.join("app"), MarkerKind::Git),
+            vec![
+                Worktree {
+                    name: "feat".into(),
+                    path: root.join("feat"),
+                },
+                Worktree {
+                    name: "fix".into(),
+                    path: PathBuf::from("/elsewhere/fix"),
+                },
+            ]
+        );
+        assert_eq!(
+            linked_worktrees(&root.join("feat"), MarkerKind::Git),
+            vec![]
+        );
+
+        let dot_git_file = [Entry {
+            name: OsStr::new(".git"),
+            is_dir: false,
+        }];
+        assert!(is_linked_worktree(&root.join("feat"), &dot_git_file));
+        let dot_git_dir = [Entry {
+            name: OsStr::new(".git"),
+            is_dir: true,
+        }];
+        assert!(!is_linked_worktree(&root.join("app"), &dot_git_dir));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use zellij_tile::prelude::SessionInfo;
+
+/// What lives in the session the plugin is running in, besides the plugin itself, as last reported
+/// by Zellij.
+///
+/// A bootstrap session, i.e. a session created only to host the plugin, is killed after switching
+/// away from it. Users may however have started working in it in the meantime, in which case it is
+/// kept.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct SessionOccupancy {
+    /// The number of panes other than the plugin's own pane. Panes that cannot be selected, e.g.
+    /// the default `tab-bar` and `status-bar` plugins, are part of the layout and not counted.
+    other_panes: usize,
+    /// The number of clients attached to the session, including the one running the plugin.
+    connected_clients: usize,
+}
+
+impl SessionOccupancy {
+    /// Takes stock of `session`, as seen from the plugin with id `plugin_id`.
+    pub(crate) fn new(session: &SessionInfo, plugin_id: u32) -> Self {
+        Self {
+            other_panes: session
+                .panes
+                .panes
+                .values()
+                .flatten()
+                .filter(|pane| !(pane.is_plugin && pane.id == plugin_id))
+                .filter(|pane| !pane.is_plugin || pane.is_selectable)
+                .count(),
+            connected_clients: session.connected_clients,
+        }
+    }
+
+    /// Why the session must be kept after switching away from it, if anything.
+    pub(crate) fn takeover_blocker(&self) -> Option<String> {
+        if self.connected_clients > 1 {
+            let others = self.connected_clients - 1;
+            Some(format!(
+                "{others} other client{} attached",
+                if others == 1 { " is" } else { "s are" }
+            ))
+        } else if self.other_panes > 0 {
+            Some(format!(
+                "{} other pane{} open",
+                self.other_panes,
+                if self.other_panes == 1 {
+                    " is"
+                } else {
+                    "s are"
+                }
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_tile::prelude::{PaneInfo, PaneManifest};
+
+    const PLUGIN_ID: u32 = 7;
+
+    fn session(panes: Vec<PaneInfo>, connected_clients: usize) -> SessionInfo {
+        SessionInfo {
+            panes: PaneManifest {
+                panes: [(0, panes)].into(),
+            },
+            connected_clients,
+            is_current_session: true,
+            ..Default::default()
+        }
+    }
+
+    fn plugin_pane(id: u32, is_selectable: bool) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_plugin: true,
+            is_selectable,
+            ..Default::default()
+        }
+    }
+
+    fn terminal_pane(id: u32) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_selectable: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn session_with_only_the_plugin_can_be_taken_over() {
+        let panes = vec![
+            plugin_pane(PLUGIN_ID, true),
+            // The default tab and status bars.
+            plugin_pane(1, false),
+            plugin_pane(2, false),
+        ];
+        let occupancy = SessionOccupancy::new(&session(panes, 1), PLUGIN_ID);
+        assert_eq!(occupancy.takeover_blocker(), None);
+    }
+
+    #[test]
+    fn session_with_other_panes_is_kept() {
+        // Terminal and plugin pane ids live in different namespaces.
+        let panes = vec![
+            plugin_pane(PLUGIN_ID, true),
+            terminal_pane(PLUGIN_ID),
+            plugin_pane(3, true),
+        ];
+        let occupancy = SessionOccupancy::new(&session(panes, 1), PLUGIN_ID);
+        assert_eq!(
+            occupancy.takeover_blocker().as_deref(),
+            Some("2 other panes are open")
+        );
+    }
+
+    #[test]
+    fn session_with_other_clients_is_kept() {
+        let panes = vec![plugin_pane(PLUGIN_ID, true), terminal_pane(1)];
+        let occupancy = SessionOccupancy::new(&session(panes, 2), PLUGIN_ID);
+        assert_eq!(
+            occupancy.takeover_blocker().as_deref(),
+            Some("1 other client is attached")
+        );
+    }
+}
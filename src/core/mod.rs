@@ -30,6 +30,22 @@ pub(crate) enum PluginError {
         session_name: String,
         reason: &'static str,
     },
+    #[error("Failed to create session {session_name:?}: {reason}")]
+    CreateSessionFailed {
+        session_name: String,
+        reason: String,
+    },
+    #[error("Kept session {session_name:?} after switching away from it: {reason}")]
+    BootstrapSessionKept {
+        session_name: String,
+        reason: String,
+    },
+    #[error("Failed to {action} session {session_name:?}: {reason}")]
+    SessionActionFailed {
+        action: &'static str,
+        session_name: String,
+        reason: &'static str,
+    },
 }
 
 /// Whether the plugin should refresh its UI.
@@ -0,0 +1,127 @@
+/// Persisted visit statistics, used to rank frequently and recently visited directories higher.
+use crate::json_store;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// The default max age, in days, of the entries kept when pruning the store.
+pub(crate) const DEFAULT_PRUNE_MAX_AGE_DAYS: u64 = 90;
+
+/// Visit statistics of a single directory.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+struct Visits {
+    /// The number of times the directory was switched to.
+    count: u32,
+    /// The time of the last visit, in seconds since the UNIX epoch.
+    last_visit: u64,
+}
+
+impl Visits {
+    /// Weighs the visit count by how recent the last visit is, in the spirit of zoxide's "frecency"
+    /// algorithm.
+    fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_visit);
+        let recency = match age {
+            _ if age < HOUR => 4.0,
+            _ if age < DAY => 2.0,
+            _ if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+        f64::from(self.count) * recency
+    }
+}
+
+/// Visit statistics of all directories switched to, keyed by absolute path.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct FrecencyStore {
+    entries: BTreeMap<PathBuf, Visits>,
+}
+
+impl FrecencyStore {
+    /// Loads the store from `path`. A missing file yields an empty store.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        json_store::load(path, "frecency")
+    }
+
+    /// Writes the store to `path`, creating parent directories as needed.
+    pub(crate) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        json_store::save(self, path, "frecency")
+    }
+
+    pub(crate) fn record_visit(&mut self, path: PathBuf, now: u64) {
+        let visits = self.entries.entry(path).or_default();
+        visits.count = visits.count.saturating_add(1);
+        visits.last_visit = now;
+    }
+
+    /// The frecency score of `path`, `0.0` if it was never visited.
+    pub(crate) fn score(&self, path: &Path, now: u64) -> f64 {
+        self.entries
+            .get(path)
+            .map(|visits| visits.score(now))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Forgets directories not visited for more than `max_age_days`. Returns the number of entries
+    /// removed.
+    pub(crate) fn prune(&mut self, max_age_days: u64, now: u64) -> usize {
+        let count = self.entries.len();
+        self.entries.retain(|_, visits| {
+            now.saturating_sub(visits.last_visit) <= max_age_days.saturating_mul(DAY)
+        });
+        count - self.entries.len()
+    }
+}
+
+/// The current time, in seconds since the UNIX epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_favors_frequent_and_recent_visits() {
+        let now = 100 * WEEK;
+        let mut store = FrecencyStore::default();
+
+        store.record_visit(PathBuf::from("/once"), now - 2 * WEEK);
+        for _ in 0..3 {
+            store.record_visit(PathBuf::from("/often"), now - 2 * WEEK);
+        }
+        store.record_visit(PathBuf::from("/recent"), now - 1);
+
+        assert_eq!(store.score(Path::new("/never"), now), 0.0);
+        assert!(store.score(Path::new("/often"), now) > store.score(Path::new("/once"), now));
+        assert!(store.score(Path::new("/recent"), now) > store.score(Path::new("/often"), now));
+    }
+
+    #[test]
+    fn prune_removes_old_entries() {
+        let now = 100 * WEEK;
+        let mut store = FrecencyStore::default();
+
+        store.record_visit(PathBuf::from("/old"), now - 10 * DAY);
+        store.record_visit(PathBuf::from("/new"), now - DAY);
+
+        assert_eq!(store.prune(7, now), 1);
+        assert_eq!(store.score(Path::new("/old"), now), 0.0);
+        assert!(store.score(Path::new("/new"), now) > 0.0);
+    }
+}
@@ -1,22 +1,35 @@
 use crate::{
     core::{InternalError, PluginError, PluginUpdateLoop},
-    matcher::{Choice, Match},
+    frecency::{self, FrecencyStore},
+    hash::{self, SessionNameTemplate},
+    keymap::InputMode,
+    line_editor::{LineEdit, LineEditOutcome, LineEditor},
+    matcher::{Choice, Match, Matcher, MatcherKind},
 };
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::{
-    path::PathBuf,
+    cmp::Ordering,
+    path::{Path, PathBuf},
     rc::{Rc, Weak},
+    time::Duration,
 };
 
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher as _;
 use itertools::Itertools;
+use project_markers::MarkerKind;
+use zellij_tile::prelude::SessionInfo;
 
-#[derive(Default, Eq, Ord, PartialEq, PartialOrd)]
+/// A directory listed in [Picker::Paths]. Entries are identified by their representation and path.
+#[derive(Default)]
 pub(super) struct PathEntry {
     repr: Option<PathBuf>,
     path: PathBuf,
+    /// The name of the session associated with [path], if it can be derived. Cached since it is
+    /// checked against the current session on every update of the list of matches. Derived when
+    /// the entry is added to the [FuzzySearchContext].
+    session_name: Option<String>,
+    /// The kind of project the directory was recognised as while scanning, if known.
+    marker: Option<MarkerKind>,
 }
 
 impl PathEntry {
@@ -24,17 +37,65 @@ impl PathEntry {
         Self {
             repr: Some(repr),
             path,
+            session_name: None,
+            marker: None,
         }
     }
 
     pub(super) fn path(&self) -> PathBuf {
         self.path.clone()
     }
+
+    pub(super) fn with_marker(self, marker: MarkerKind) -> Self {
+        Self {
+            marker: Some(marker),
+            ..self
+        }
+    }
+
+    pub(super) fn session_name(&self) -> Option<&str> {
+        self.session_name.as_deref()
+    }
+
+    pub(super) fn marker(&self) -> Option<MarkerKind> {
+        self.marker
+    }
 }
 
 impl From<PathBuf> for PathEntry {
     fn from(path: PathBuf) -> Self {
-        Self { repr: None, path }
+        Self {
+            repr: None,
+            path,
+            session_name: None,
+            marker: None,
+        }
+    }
+}
+
+impl PathEntry {
+    fn key(&self) -> (&Option<PathBuf>, &PathBuf) {
+        (&self.repr, &self.path)
+    }
+}
+
+impl PartialEq for PathEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for PathEntry {}
+
+impl PartialOrd for PathEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PathEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
     }
 }
 
@@ -48,28 +109,149 @@ impl Choice for PathEntry {
     }
 }
 
+/// The list displayed by the plugin.
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Picker {
+    /// Directories to open a session in.
+    #[default]
+    Paths,
+    /// Existing sessions, to switch to or manage.
+    Sessions,
+}
+
+/// The state of a session, in the order sessions are listed.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum SessionStatus {
+    /// The session the plugin is running in.
+    Current,
+    Running,
+    /// The session can be resurrected.
+    Exited,
+}
+
+/// A session listed in [Picker::Sessions]. Sessions are identified by their status and name.
+pub(crate) struct SessionEntry {
+    pub(crate) name: String,
+    pub(crate) status: SessionStatus,
+    pub(crate) tab_count: usize,
+    pub(crate) connected_clients: usize,
+    /// How long ago an exited session exited.
+    pub(crate) exited_for: Option<Duration>,
+    /// The directory associated with the session, if it is one of the choices of [Picker::Paths].
+    pub(crate) path: Option<PathBuf>,
+}
+
+impl SessionEntry {
+    pub(super) fn running(info: &SessionInfo) -> Self {
+        Self {
+            name: info.name.clone(),
+            status: match info.is_current_session {
+                true => SessionStatus::Current,
+                false => SessionStatus::Running,
+            },
+            tab_count: info.tabs.len(),
+            connected_clients: info.connected_clients,
+            exited_for: None,
+            path: None,
+        }
+    }
+
+    pub(super) fn exited(name: String, exited_for: Duration) -> Self {
+        Self {
+            name,
+            status: SessionStatus::Exited,
+            tab_count: 0,
+            connected_clients: 0,
+            exited_for: Some(exited_for),
+            path: None,
+        }
+    }
+
+    fn key(&self) -> (SessionStatus, &str) {
+        (self.status, &self.name)
+    }
+}
+
+impl PartialEq for SessionEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SessionEntry {}
+
+impl PartialOrd for SessionEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SessionEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Choice for SessionEntry {
+    fn repr(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Confirmation or input expected from the user before acting on a session.
+pub(crate) enum SessionPrompt {
+    /// Kills the session once the user confirms.
+    ConfirmKill(String),
+    /// Renames the current session once the user validates its new name.
+    Rename {
+        session_name: String,
+        new_name: LineEditor,
+    },
+    /// Renames the running session opened for a directory under an older naming scheme once the
+    /// user confirms, or switches to it as is if they decline.
+    ConfirmMigration {
+        legacy_name: String,
+        session_name: String,
+        relative_cwd: PathBuf,
+    },
+}
+
 struct FuzzyMatcher {
-    matcher: SkimMatcherV2,
+    kind: MatcherKind,
+    matcher: Box<dyn Matcher>,
 }
 
 impl Default for FuzzyMatcher {
     fn default() -> Self {
-        Self {
-            matcher: SkimMatcherV2::default().use_cache(true),
-        }
+        Self::new(MatcherKind::default())
     }
 }
 
 impl FuzzyMatcher {
-    fn apply<C: Choice>(&self, input: &str, choices: &BTreeSet<Rc<C>>) -> Vec<Match<C>> {
+    fn new(kind: MatcherKind) -> Self {
+        Self {
+            kind,
+            matcher: kind.matcher(),
+        }
+    }
+
+    /// Matches `choices` against the terms of `input`, sorted by decreasing score. `boost` is added
+    /// to the score of each matching choice. See [crate::matcher::Query] for the supported syntax.
+    fn apply<C: Choice>(
+        &self,
+        input: &str,
+        choices: &BTreeSet<Rc<C>>,
+        boost: impl Fn(&C) -> i64,
+    ) -> Vec<Match<C>> {
+        let query = self.kind.parse_query(input);
         choices
             .iter()
             .filter_map(|choice| {
-                self.matcher
-                    .fuzzy_indices(choice.repr(), input)
+                query
+                    .match_indices(self.matcher.as_ref(), choice.repr())
                     .map(|(score, indices)| {
                         (
-                            score,
+                            score + boost(choice),
                             Match {
                                 indices,
                                 choice: Rc::downgrade(choice),
@@ -83,46 +265,204 @@ impl FuzzyMatcher {
     }
 }
 
+/// An asynchronous source of choices.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum ScanSource {
+    /// A `file_system` worker crawl of the given root, relative to the plugin's CWD.
+    Crawler(PathBuf),
+    /// A crawl of the given root by [crate::protocol::PathFinderPluginConfig::host_scan_program],
+    /// for roots the plugin cannot access.
+    HostCrawler(PathBuf),
+    /// An external program listing directories.
+    ExternalProgram(PathBuf),
+}
+
+impl ScanSource {
+    /// A short name for this source, suitable for display.
+    fn label(&self) -> String {
+        match self {
+            ScanSource::Crawler(root) | ScanSource::HostCrawler(root) => root.display().to_string(),
+            ScanSource::ExternalProgram(program) => program
+                .file_name()
+                .unwrap_or(program.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum ScanStatus {
+    Pending,
+    Complete,
+    Failed,
+}
+
+/// How to display the entry associated with the session the plugin is running in.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum CurrentSessionDisplay {
+    /// Remove the entry from the list of matches.
+    #[default]
+    Hide,
+    /// Keep the entry in the list of matches, with a badge.
+    Mark,
+}
+
+/// Session-related information displayed next to an entry.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum SessionBadge {
+    /// The entry is associated with the session the plugin is running in.
+    Current,
+    /// The entry is associated with a running session: selecting it switches to that session.
+    Active,
+    /// The entry is associated with an exited session: selecting it resurrects that session.
+    Resurrectable,
+}
+
+/// Added to the score of entries associated with a running session when
+/// [FuzzySearchContext::boost_active_sessions] is set.
+const ACTIVE_SESSION_SCORE_BOOST: i64 = 32;
+
+/// Multiplier applied to frecency scores before adding them to the matcher score.
+const FRECENCY_SCORE_WEIGHT: f64 = 4.0;
+
+/// Upper bound of the frecency boost, so that frequently visited entries do not drown better
+/// matches.
+const MAX_FRECENCY_SCORE_BOOST: i64 = 96;
+
+/// A snapshot of the progress of all scan sources, for display purposes.
+pub(crate) struct ScanProgress {
+    /// The number of sources that have not reported yet.
+    pub(crate) pending: usize,
+    /// The labels of the sources that failed to report.
+    pub(crate) failed: Vec<String>,
+    /// Monotonic counter used to animate the activity indicator.
+    pub(crate) tick: usize,
+}
+
+/// A list of choices filtered against the user input, along with the selection.
+struct ChoiceList<C: Choice> {
+    /// The unfiltered list of elements to run the fuzzy matcher on.
+    choices: BTreeSet<Rc<C>>,
+
+    /// List of choice indices from [Self::choices].
+    matches: Vec<Match<C>>,
+
+    /// The index of the currently selected choice relative to [Self::matches].
+    selected_index: usize,
+
+    /// The currently selected choice, used to keep the selection on the same entry when the list of
+    /// matches changes.
+    ///
+    /// For example, given the current set of results and selection cursor:
+    ///
+    /// ```text
+    ///     nix-config
+    ///     nix-config-ghostty
+    ///   > nix-config-nvim
+    ///     nix-config-manager
+    /// ```
+    ///
+    /// And assuming the next user input changes the results to:
+    ///
+    /// ```text
+    ///     nix-config-ghostty
+    ///   > nix-config-nvim
+    ///     nix-config-manager
+    /// ```
+    ///
+    /// Keeping track of the selected match allows us to move the cursor to the second entry (the one
+    /// the user manually selected already) instead of leaving it on the third one. The same applies
+    /// when new choices are streamed in and shift indices under the cursor.
+    selected_match: Option<Weak<C>>,
+}
+
+impl<C: Choice> Default for ChoiceList<C> {
+    fn default() -> Self {
+        Self {
+            choices: BTreeSet::new(),
+            matches: Vec::new(),
+            selected_index: 0,
+            selected_match: None,
+        }
+    }
+}
+
+impl<C: Choice + Ord> ChoiceList<C> {
+    /// The selected choice, or the index of the selection if it is out of bounds.
+    fn selected_match(&self) -> std::result::Result<Option<Rc<C>>, usize> {
+        if let Some(selected_match) = self.selected_match.as_ref().and_then(Weak::upgrade) {
+            return Ok(Some(selected_match));
+        }
+
+        match self.matches.get(self.selected_index) {
+            Some(selected_match) => Ok(selected_match.choice.upgrade()),
+            None => Err(self.selected_index),
+        }
+    }
+
+    /// Moves the selection to `index`, clamped to the list of matches. Returns whether the
+    /// selection moved.
+    fn select_index(&mut self, index: usize) -> bool {
+        let previous_index = self.selected_index;
+        self.selected_index = index.clamp(0, self.matches.len().saturating_sub(1));
+        self.selected_match = self.match_at_selected_index();
+        previous_index != self.selected_index
+    }
+
+    fn match_at_selected_index(&self) -> Option<Weak<C>> {
+        self.matches
+            .get(self.selected_index)
+            .map(|m| m.choice.clone())
+    }
+
+    /// Replaces all choices, keeping the selection on the choice equal to the selected one, if any.
+    /// [Self::set_matches] must be called afterwards.
+    fn replace_choices(&mut self, choices: BTreeSet<Rc<C>>) {
+        let selected_match = self.selected_match.as_ref().and_then(Weak::upgrade);
+        self.choices = choices;
+        self.selected_match = selected_match
+            .and_then(|selected| self.choices.get(selected.as_ref()))
+            .map(Rc::downgrade);
+    }
+
+    fn set_matches(&mut self, matches: Vec<Match<C>>) {
+        self.matches = matches;
+
+        // Follow the selected match if it is still visible, otherwise clamp selected_index.
+        let selected_match_index = self.selected_match.as_ref().and_then(|selected| {
+            self.matches
+                .iter()
+                .position(|m| Weak::ptr_eq(&m.choice, selected))
+        });
+        self.selected_index = selected_match_index
+            .unwrap_or(self.selected_index)
+            .clamp(0, self.matches.len().saturating_sub(1));
+        self.selected_match = self.match_at_selected_index();
+    }
+}
+
 /// The plugin context holds volatile state such as non-fatal errors that should be reported to the
 /// user via the UI.
 #[derive(Default)]
 pub(crate) struct FuzzySearchContext {
-    /// The user input query.
-    user_input: String,
+    /// The user input query, along with the cursor position.
+    user_input: LineEditor,
 
-    /// The unfiltered list of elements to run the fuzzy matcher on.
-    choices: BTreeSet<Rc<PathEntry>>,
+    /// The picker currently displayed.
+    picker: Picker,
 
-    /// List of choice indices from [choices].
-    matches: Vec<Match<PathEntry>>,
+    /// The directories to pick from, in [Picker::Paths].
+    paths: ChoiceList<PathEntry>,
 
-    /// The index of the currently selected choice relative to [filtered_choices].
-    selected_index: usize,
+    /// The sessions to pick from, in [Picker::Sessions].
+    sessions: ChoiceList<SessionEntry>,
 
-    // TODO: Add support for tracking the selected match: i.e. instead of only tracking the
-    // selected index, also track the selected match in case it appears in successive results.
-    //
-    // For example, given the current set of results and selection cursor:
-    //
-    // ```
-    //     nix-config
-    //     nix-config-ghostty
-    //   > nix-config-nvim
-    //     nix-config-manager
-    // ```
-    //
-    // And assuming the next user input changes the results to:
-    //
-    // ```
-    //     nix-config-ghostty
-    //   > nix-config-nvim
-    //     nix-config-manager
-    // ```
-    //
-    // Keeping track of the selected match would allow us to move the cursor to the second entry
-    // (the one the user manually selected already) instead of leaving it on the third one.
-    #[allow(dead_code)]
-    selected_match: Option<Weak<PathEntry>>,
+    /// The confirmation or input expected from the user before acting on a session, if any.
+    session_prompt: Option<SessionPrompt>,
+
+    /// The choices marked for a batch action, independently of the list of matches.
+    marked: BTreeSet<Rc<PathEntry>>,
 
     /// Non-fatal errors raised during plugin execution. While non-fatal, some errors may not be
     /// recoverable.
@@ -130,37 +470,137 @@ pub(crate) struct FuzzySearchContext {
 
     /// Matches the list of repositories against the user input. Keeps track of the user input.
     matcher: FuzzyMatcher,
+
+    /// The status of each source of choices that was requested since the plugin started.
+    scan_sources: BTreeMap<ScanSource, ScanStatus>,
+
+    /// Advanced while scans are pending to animate the activity indicator.
+    spinner_tick: usize,
+
+    /// The name of the session the plugin is running in, if known.
+    current_session_name: Option<String>,
+    /// The name of all running sessions, including the current one.
+    active_session_names: BTreeSet<String>,
+    /// The name of all exited sessions that can be resurrected.
+    resurrectable_session_names: BTreeSet<String>,
+    /// Whether to rank entries associated with a running session higher.
+    boost_active_sessions: bool,
+
+    /// Derives the name of the session of each directory.
+    session_name_template: SessionNameTemplate,
+    /// The directory owning each session name rendered by [session_name_template], i.e. the
+    /// smallest path rendered to it. Other directories rendered to the same name are disambiguated
+    /// with a hash of their path.
+    session_name_claims: BTreeMap<String, PathBuf>,
+
+    /// Visit statistics used to rank frequently and recently visited entries higher.
+    frecency: FrecencyStore,
+    /// The directory entry paths are relative to, used to look them up in [frecency].
+    frecency_root: PathBuf,
+
+    /// How to display the entry associated with the current session.
+    current_session_display: CurrentSessionDisplay,
+
+    /// The mode of the keymap, if it is modal.
+    input_mode: Option<InputMode>,
 }
 
 impl FuzzySearchContext {
     pub(super) fn user_input(&self) -> &str {
-        &self.user_input
+        self.user_input.as_str()
     }
 
+    /// The byte offset of the cursor in [Self::user_input].
+    pub(super) fn user_input_cursor(&self) -> usize {
+        self.user_input.cursor()
+    }
+
+    /// The index of the selection in the list of matches of the current picker.
     pub(super) fn selected_index(&self) -> usize {
-        self.selected_index
+        match self.picker {
+            Picker::Paths => self.paths.selected_index,
+            Picker::Sessions => self.sessions.selected_index,
+        }
     }
 
+    /// The selected directory, regardless of the current picker.
     pub(super) fn selected_match(&mut self) -> Option<Rc<PathEntry>> {
-        // TODO: Use self.selected_match which should keep track of the selected item in a more
-        // stable fashion.
-        //self.selected_match
-        //    .as_ref()
-        //    .and_then(Weak::<PathEntry>::upgrade)
-
-        // NOTE: fallback on self.selected_index for now.
-        let Some(selected_match) = self.matches.get(self.selected_index) else {
-            self.log_internal_error(InternalError::SelectionIndexOutOfBounds(
-                self.selected_index,
-            ));
-            return None;
+        self.paths.selected_match().unwrap_or_else(|index| {
+            self.log_internal_error(InternalError::SelectionIndexOutOfBounds(index));
+            None
+        })
+    }
+
+    /// The selected session, regardless of the current picker.
+    pub(super) fn selected_session(&mut self) -> Option<Rc<SessionEntry>> {
+        self.sessions.selected_match().unwrap_or_else(|index| {
+            self.log_internal_error(InternalError::SelectionIndexOutOfBounds(index));
+            None
+        })
+    }
+
+    pub(super) fn picker(&self) -> Picker {
+        self.picker
+    }
+
+    /// Switches between the directory and session pickers. The user input is kept.
+    pub(super) fn toggle_picker(&mut self) -> PluginUpdateLoop {
+        self.picker = match self.picker {
+            Picker::Paths => Picker::Sessions,
+            Picker::Sessions => Picker::Paths,
+        };
+        self.session_prompt = None;
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn session_prompt(&self) -> Option<&SessionPrompt> {
+        self.session_prompt.as_ref()
+    }
+
+    /// Asks the user for confirmation or input before acting on a session.
+    pub(super) fn set_session_prompt(&mut self, prompt: SessionPrompt) -> PluginUpdateLoop {
+        self.session_prompt = Some(prompt);
+        PluginUpdateLoop::MarkDirty
+    }
+
+    /// Dismisses the session prompt, and returns it.
+    pub(super) fn take_session_prompt(&mut self) -> Option<SessionPrompt> {
+        self.session_prompt.take()
+    }
+
+    /// Marks the selected match, or unmarks it if it is already marked, and moves the selection to
+    /// the next match.
+    pub(super) fn toggle_mark(&mut self) -> PluginUpdateLoop {
+        let Some(selected_match) = self.selected_match() else {
+            return PluginUpdateLoop::NoUpdates;
         };
-        selected_match.choice.upgrade()
+        if !self.marked.remove(&selected_match) {
+            self.marked.insert(selected_match);
+        }
+        self.select_down();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn is_marked(&self, entry: &PathEntry) -> bool {
+        self.marked.contains(entry)
+    }
+
+    pub(super) fn marked_count(&self) -> usize {
+        self.marked.len()
+    }
+
+    /// Unmarks all choices and returns them, or the selected match if none was marked.
+    pub(super) fn take_marked(&mut self) -> Vec<Rc<PathEntry>> {
+        if self.marked.is_empty() {
+            return self.selected_match().into_iter().collect();
+        }
+        std::mem::take(&mut self.marked).into_iter().collect()
     }
 
     pub(super) fn on_user_input(&mut self, ch: char) -> PluginUpdateLoop {
         self.clear_errors();
-        self.user_input.push(ch);
+        self.user_input.insert(ch);
 
         self.invalidate_matches();
 
@@ -169,15 +609,17 @@ impl FuzzySearchContext {
         PluginUpdateLoop::MarkDirty
     }
 
-    pub(super) fn remove_trailing_char(&mut self) -> PluginUpdateLoop {
+    pub(super) fn edit_user_input(&mut self, edit: LineEdit) -> PluginUpdateLoop {
         let update = self.clear_errors();
 
-        if self.user_input.pop().is_some() {
-            self.invalidate_matches();
-            return PluginUpdateLoop::MarkDirty;
+        match self.user_input.apply(edit) {
+            LineEditOutcome::Unchanged => update,
+            LineEditOutcome::Moved => PluginUpdateLoop::MarkDirty,
+            LineEditOutcome::Edited => {
+                self.invalidate_matches();
+                PluginUpdateLoop::MarkDirty
+            }
         }
-
-        update
     }
 
     pub(super) fn clear_user_input(&mut self) -> PluginUpdateLoop {
@@ -193,54 +635,393 @@ impl FuzzySearchContext {
     }
 
     pub(super) fn select_up(&mut self) -> PluginUpdateLoop {
-        let update = self.clear_errors();
-        let previous_index = self.selected_index;
-        self.selected_index = self
-            .selected_index
-            .saturating_sub(1)
-            .clamp(0, self.matches.len().saturating_sub(1));
-        update | PluginUpdateLoop::from(previous_index != self.selected_index)
+        self.select_index(self.selected_index().saturating_sub(1))
     }
 
     pub(super) fn select_down(&mut self) -> PluginUpdateLoop {
+        self.select_index(self.selected_index().saturating_add(1))
+    }
+
+    pub(super) fn select_page_up(&mut self, page_size: usize) -> PluginUpdateLoop {
+        self.select_index(self.selected_index().saturating_sub(page_size))
+    }
+
+    pub(super) fn select_page_down(&mut self, page_size: usize) -> PluginUpdateLoop {
+        self.select_index(self.selected_index().saturating_add(page_size))
+    }
+
+    pub(super) fn select_first(&mut self) -> PluginUpdateLoop {
+        self.select_index(0)
+    }
+
+    pub(super) fn select_last(&mut self) -> PluginUpdateLoop {
+        self.select_index(usize::MAX)
+    }
+
+    /// Moves the selection of the current picker to `index`, clamped to the list of matches.
+    fn select_index(&mut self, index: usize) -> PluginUpdateLoop {
         let update = self.clear_errors();
-        let previous_index = self.selected_index;
-        self.selected_index = self
-            .selected_index
-            .saturating_add(1)
-            .clamp(0, self.matches.len().saturating_sub(1));
-        update | PluginUpdateLoop::from(previous_index != self.selected_index)
+        let moved = match self.picker {
+            Picker::Paths => self.paths.select_index(index),
+            Picker::Sessions => self.sessions.select_index(index),
+        };
+        update | PluginUpdateLoop::from(moved)
     }
 
     #[cfg(feature = "zellij_fallback_fs_api")]
     pub(super) fn add_choice(&mut self, choice: PathEntry) -> PluginUpdateLoop {
-        self.choices.insert(choice.into());
-        self.invalidate_matches();
-
-        PluginUpdateLoop::MarkDirty
+        self.add_choices(std::iter::once(choice))
     }
 
     pub(super) fn add_choices(
         &mut self,
         choices: impl Iterator<Item = PathEntry>,
     ) -> PluginUpdateLoop {
-        self.choices
-            .extend(choices.map(Into::<Rc<PathEntry>>::into));
+        // Claim all names first, since a later choice may take the name of an earlier one.
+        let choices = choices.collect::<Vec<_>>();
+        for choice in &choices {
+            self.claim_session_name(&choice.path);
+        }
+        let choices = choices
+            .into_iter()
+            .map(|mut choice| {
+                choice.session_name = self.session_name(&choice.path).ok();
+                Rc::new(choice)
+            })
+            .collect::<Vec<_>>();
+        self.paths.choices.extend(choices);
         self.invalidate_matches();
 
         PluginUpdateLoop::MarkDirty
     }
 
+    /// Claims the session name of `path` if no smaller path is rendered to the same name, so that
+    /// names do not depend on the order directories are discovered in. The choice of the former
+    /// owner is renamed to its disambiguated variant.
+    fn claim_session_name(&mut self, path: &PathBuf) {
+        let Ok(name) = self.session_name_template.render(path) else {
+            return;
+        };
+        let owner = self
+            .session_name_claims
+            .entry(name.clone())
+            .or_insert_with(|| path.clone());
+        if path >= owner {
+            return;
+        }
+        let former_owner = std::mem::replace(owner, path.clone());
+        self.rename_choice(&former_owner, &name);
+    }
+
+    /// Replaces the session name of the choice for `path`, previously known as `name`, with its
+    /// disambiguated variant.
+    fn rename_choice(&mut self, path: &Path, name: &str) {
+        let choices = self
+            .paths
+            .choices
+            .iter()
+            .map(|choice| {
+                if choice.path != path {
+                    return choice.clone();
+                }
+                let renamed = Rc::new(PathEntry {
+                    repr: choice.repr.clone(),
+                    path: choice.path.clone(),
+                    session_name: hash::collision_fallback(name, path).ok(),
+                    marker: choice.marker,
+                });
+                if self.marked.remove(choice) {
+                    self.marked.insert(renamed.clone());
+                }
+                renamed
+            })
+            .collect();
+        self.paths.replace_choices(choices);
+    }
+
+    /// The name of the session of the directory at `path`, relative to the plugin's CWD.
+    pub(super) fn session_name(&self, path: &Path) -> anyhow::Result<String> {
+        let name = self.session_name_template.render(path)?;
+        match self.session_name_claims.get(&name) {
+            Some(owner) if owner != path => hash::collision_fallback(&name, path),
+            _ => Ok(name),
+        }
+    }
+
+    /// The number of choices in the current picker.
     pub(super) fn choice_count(&self) -> usize {
-        self.choices.len()
+        match self.picker {
+            Picker::Paths => self.paths.choices.len(),
+            Picker::Sessions => self.sessions.choices.len(),
+        }
     }
 
+    /// The number of matches in the current picker.
     pub(super) fn match_count(&self) -> usize {
-        self.matches.len()
+        match self.picker {
+            Picker::Paths => self.paths.matches.len(),
+            Picker::Sessions => self.sessions.matches.len(),
+        }
     }
 
     pub(super) fn matches(&self) -> impl Iterator<Item = &Match<PathEntry>> {
-        self.matches.iter()
+        self.paths.matches.iter()
+    }
+
+    pub(super) fn session_matches(&self) -> impl Iterator<Item = &Match<SessionEntry>> {
+        self.sessions.matches.iter()
+    }
+
+    /// The mode of the keymap, or `None` if the keymap isn't modal.
+    pub(super) fn input_mode(&self) -> Option<InputMode> {
+        self.input_mode
+    }
+
+    /// Enables switching between input modes, starting in [InputMode::Insert].
+    pub(super) fn enable_input_modes(&mut self) {
+        self.input_mode = Some(InputMode::Insert);
+    }
+
+    /// Switches to `mode`, unless input modes are disabled.
+    pub(super) fn set_input_mode(&mut self, mode: InputMode) -> PluginUpdateLoop {
+        match self.input_mode {
+            Some(current) if current != mode => {
+                self.input_mode = Some(mode);
+                PluginUpdateLoop::MarkDirty
+            }
+            _ => PluginUpdateLoop::NoUpdates,
+        }
+    }
+
+    pub(super) fn matcher_kind(&self) -> MatcherKind {
+        self.matcher.kind
+    }
+
+    pub(super) fn set_matcher_kind(&mut self, kind: MatcherKind) -> PluginUpdateLoop {
+        if self.matcher.kind == kind {
+            return PluginUpdateLoop::NoUpdates;
+        }
+        self.matcher = FuzzyMatcher::new(kind);
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    /// Switches to the next available matcher.
+    pub(super) fn cycle_matcher_kind(&mut self) -> PluginUpdateLoop {
+        self.set_matcher_kind(self.matcher.kind.next())
+    }
+
+    pub(super) fn set_current_session_display(&mut self, display: CurrentSessionDisplay) {
+        self.current_session_display = display;
+    }
+
+    /// The name of an existing session opened for the directory at `path` under an older naming
+    /// scheme, if any: either `alias`, the name last recorded for the directory, or the name it was
+    /// given before templates were introduced.
+    pub(super) fn legacy_session_name(&self, path: &Path, alias: Option<&str>) -> Option<String> {
+        let session_name = self.session_name(path).ok()?;
+        alias
+            .map(str::to_string)
+            .into_iter()
+            .chain(hash::legacy_session_name(path).ok())
+            .find(|name| *name != session_name && self.has_session(name))
+    }
+
+    /// Must be set before any choice is added.
+    pub(super) fn set_session_name_template(&mut self, template: SessionNameTemplate) {
+        self.session_name_template = template;
+    }
+
+    pub(super) fn set_boost_active_sessions(&mut self, boost_active_sessions: bool) {
+        self.boost_active_sessions = boost_active_sessions;
+    }
+
+    pub(super) fn set_frecency(
+        &mut self,
+        frecency: FrecencyStore,
+        root: PathBuf,
+    ) -> PluginUpdateLoop {
+        self.frecency = frecency;
+        self.frecency_root = root;
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn frecency(&self) -> &FrecencyStore {
+        &self.frecency
+    }
+
+    /// Records a visit of `path`, relative to the plugin's CWD.
+    pub(super) fn record_visit(&mut self, path: &PathBuf) {
+        self.frecency
+            .record_visit(self.frecency_root.join(path), frecency::now());
+    }
+
+    pub(super) fn reset_frecency(&mut self) -> PluginUpdateLoop {
+        self.frecency.reset();
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn prune_frecency(&mut self, max_age_days: u64) -> PluginUpdateLoop {
+        if self.frecency.prune(max_age_days, frecency::now()) == 0 {
+            return PluginUpdateLoop::NoUpdates;
+        }
+
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    /// Updates the sessions known to the Zellij daemon, as reported by `Event::SessionUpdate`.
+    pub(super) fn set_sessions(
+        &mut self,
+        current_session_name: Option<String>,
+        active_session_names: BTreeSet<String>,
+        resurrectable_session_names: BTreeSet<String>,
+    ) -> PluginUpdateLoop {
+        if self.current_session_name == current_session_name
+            && self.active_session_names == active_session_names
+            && self.resurrectable_session_names == resurrectable_session_names
+        {
+            return PluginUpdateLoop::NoUpdates;
+        }
+
+        self.current_session_name = current_session_name;
+        self.active_session_names = active_session_names;
+        self.resurrectable_session_names = resurrectable_session_names;
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    /// Updates the details of the sessions listed in [Picker::Sessions]. Exited sessions are listed
+    /// after running ones.
+    pub(super) fn set_session_entries(
+        &mut self,
+        entries: impl Iterator<Item = SessionEntry>,
+    ) -> PluginUpdateLoop {
+        let entries = entries
+            .map(|mut entry| {
+                entry.path = self
+                    .paths
+                    .choices
+                    .iter()
+                    .find(|choice| choice.session_name() == Some(entry.name.as_str()))
+                    .map(|choice| choice.path());
+                Rc::new(entry)
+            })
+            .collect();
+        self.sessions.replace_choices(entries);
+        if self.picker != Picker::Sessions {
+            return PluginUpdateLoop::NoUpdates;
+        }
+
+        self.invalidate_matches();
+        PluginUpdateLoop::MarkDirty
+    }
+
+    fn is_current_session(&self, entry: &PathEntry) -> bool {
+        self.current_session_name.is_some()
+            && entry.session_name() == self.current_session_name.as_deref()
+    }
+
+    /// The badge to display next to `entry`, if any.
+    pub(super) fn session_badge(&self, entry: &PathEntry) -> Option<SessionBadge> {
+        let session_name = entry.session_name()?;
+        if self.is_current_session(entry) {
+            Some(SessionBadge::Current)
+        } else if self.active_session_names.contains(session_name) {
+            Some(SessionBadge::Active)
+        } else if self.resurrectable_session_names.contains(session_name) {
+            Some(SessionBadge::Resurrectable)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a running or exited session is named `session_name`.
+    pub(super) fn has_session(&self, session_name: &str) -> bool {
+        self.current_session_name.as_deref() == Some(session_name)
+            || self.active_session_names.contains(session_name)
+            || self.resurrectable_session_names.contains(session_name)
+    }
+
+    /// A name derived from `session_name` that no running or exited session uses yet, e.g.
+    /// `{session_name}-2`.
+    pub(super) fn fresh_session_name(&self, session_name: &str) -> String {
+        (2..)
+            .map(|suffix| format!("{session_name}-{suffix}"))
+            .find(|name| !self.has_session(name))
+            .expect("there are less sessions than suffixes")
+    }
+
+    fn score_boost(&self, entry: &PathEntry, now: u64) -> i64 {
+        let is_active = entry
+            .session_name()
+            .is_some_and(|name| self.active_session_names.contains(name));
+        let session_boost = if self.boost_active_sessions && is_active {
+            ACTIVE_SESSION_SCORE_BOOST
+        } else {
+            0
+        };
+
+        let frecency = self
+            .frecency
+            .score(&self.frecency_root.join(&entry.path), now);
+        let frecency_boost =
+            ((frecency * FRECENCY_SCORE_WEIGHT) as i64).min(MAX_FRECENCY_SCORE_BOOST);
+
+        session_boost + frecency_boost
+    }
+
+    /// Marks `source` as pending until [FuzzySearchContext::complete_scan] or
+    /// [FuzzySearchContext::fail_scan] is called for it.
+    pub(super) fn start_scan(&mut self, source: ScanSource) -> PluginUpdateLoop {
+        self.scan_sources.insert(source, ScanStatus::Pending);
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn complete_scan(&mut self, source: ScanSource) -> PluginUpdateLoop {
+        self.scan_sources.insert(source, ScanStatus::Complete);
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn fail_scan(&mut self, source: ScanSource) -> PluginUpdateLoop {
+        self.scan_sources.insert(source, ScanStatus::Failed);
+        PluginUpdateLoop::MarkDirty
+    }
+
+    /// Whether some sources have not reported yet.
+    pub(super) fn is_scanning(&self) -> bool {
+        self.scan_sources
+            .values()
+            .any(|status| *status == ScanStatus::Pending)
+    }
+
+    /// Advances the activity indicator. Only requires a redraw while scans are pending.
+    pub(super) fn tick_spinner(&mut self) -> PluginUpdateLoop {
+        if !self.is_scanning() {
+            return PluginUpdateLoop::NoUpdates;
+        }
+
+        self.spinner_tick = self.spinner_tick.wrapping_add(1);
+        PluginUpdateLoop::MarkDirty
+    }
+
+    pub(super) fn scan_progress(&self) -> ScanProgress {
+        ScanProgress {
+            pending: self
+                .scan_sources
+                .values()
+                .filter(|status| **status == ScanStatus::Pending)
+                .count(),
+            failed: self
+                .scan_sources
+                .iter()
+                .filter(|(_, status)| **status == ScanStatus::Failed)
+                .map(|(source, _)| source.label())
+                .collect(),
+            tick: self.spinner_tick,
+        }
     }
 
     pub(super) fn log_error(&mut self, error: PluginError) -> PluginUpdateLoop {
@@ -265,14 +1046,358 @@ impl FuzzySearchContext {
         &self.errors
     }
 
+    /// Refilters the choices of the current picker. The other picker is refiltered when toggled.
     fn invalidate_matches(&mut self) {
-        self.matches = self.matcher.apply(&self.user_input, &self.choices);
+        if self.picker == Picker::Sessions {
+            let matches =
+                self.matcher
+                    .apply(self.user_input.as_str(), &self.sessions.choices, |_| 0);
+            self.sessions.set_matches(matches);
+            return;
+        }
 
-        // Clamp selected_index.
-        self.selected_index = self
-            .selected_index
-            .clamp(0, self.matches.len().saturating_sub(1));
+        let now = frecency::now();
+        let mut matches =
+            self.matcher
+                .apply(self.user_input.as_str(), &self.paths.choices, |entry| {
+                    self.score_boost(entry, now)
+                });
+        if self.current_session_display == CurrentSessionDisplay::Hide {
+            matches.retain(|m| {
+                m.choice
+                    .upgrade()
+                    .is_some_and(|choice| !self.is_current_session(&choice))
+            });
+        }
+        self.paths.set_matches(matches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_name(path: &str) -> String {
+        SessionNameTemplate::default()
+            .render(Path::new(path))
+            .unwrap()
+    }
+
+    fn context_with_choices(choices: &[&str]) -> FuzzySearchContext {
+        let mut context = FuzzySearchContext::default();
+        context.add_choices(choices.iter().map(|c| PathBuf::from(c).into()));
+        context
+    }
+
+    fn select(context: &mut FuzzySearchContext, repr: &str) {
+        context.select_first();
+        while context.selected_match().unwrap().repr() != repr {
+            assert!(context.select_down().as_bool(), "{repr} not found");
+        }
+    }
+
+    #[test]
+    fn selection_follows_match_on_user_input() {
+        let mut context = context_with_choices(&[
+            "nix-config",
+            "nix-config-ghostty",
+            "nix-config-nvim",
+            "nix-config-manager",
+        ]);
+        select(&mut context, "nix-config-nvim");
+
+        context.on_user_input('g');
+        context.on_user_input('-');
+
+        assert_eq!(context.match_count(), 3);
+        assert_eq!(context.selected_match().unwrap().repr(), "nix-config-nvim");
+    }
+
+    #[test]
+    fn selection_follows_match_on_new_choices() {
+        let mut context = context_with_choices(&["b", "d"]);
+        select(&mut context, "d");
+
+        context.add_choices(["a", "c"].into_iter().map(|c| PathBuf::from(c).into()));
+
+        assert_eq!(context.match_count(), 4);
+        assert_eq!(context.selected_match().unwrap().repr(), "d");
+    }
+
+    #[test]
+    fn current_session_is_hidden() {
+        let mut context = context_with_choices(&["a/foo", "b/foo"]);
+        let current = session_name("a/foo");
+
+        context.set_sessions(Some(current.clone()), [current].into(), BTreeSet::new());
+
+        assert_eq!(context.match_count(), 1);
+        assert_eq!(context.selected_match().unwrap().repr(), "b/foo");
+    }
+
+    fn badges(context: &FuzzySearchContext) -> Vec<(String, Option<SessionBadge>)> {
+        context
+            .matches()
+            .map(|m| {
+                let entry = m.choice.upgrade().unwrap();
+                (entry.repr().to_string(), context.session_badge(&entry))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn current_session_is_marked() {
+        let mut context = context_with_choices(&["a/foo", "b/foo"]);
+        let current = session_name("a/foo");
+
+        context.set_current_session_display(CurrentSessionDisplay::Mark);
+        context.set_sessions(Some(current.clone()), [current].into(), BTreeSet::new());
+
+        assert_eq!(
+            badges(&context),
+            vec![
+                ("a/foo".to_string(), Some(SessionBadge::Current)),
+                ("b/foo".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn active_sessions_are_badged_and_boosted() {
+        let mut context = context_with_choices(&["a", "b", "c"]);
+        let active = session_name("c");
+        let resurrectable = session_name("b");
+
+        context.set_boost_active_sessions(true);
+        context.set_sessions(None, [active].into(), [resurrectable].into());
+
+        assert_eq!(
+            badges(&context),
+            vec![
+                ("c".to_string(), Some(SessionBadge::Active)),
+                ("a".to_string(), None),
+                ("b".to_string(), Some(SessionBadge::Resurrectable)),
+            ]
+        );
+    }
+
+    fn reprs(entries: Vec<Rc<PathEntry>>) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| entry.repr().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn toggle_mark_moves_selection_down() {
+        let mut context = context_with_choices(&["a", "b", "c"]);
+        select(&mut context, "a");
+
+        context.toggle_mark();
+        context.toggle_mark();
+        assert_eq!(context.marked_count(), 2);
+        assert_eq!(context.selected_match().unwrap().repr(), "c");
+
+        // Marks survive the list of matches changing.
+        context.on_user_input('c');
+        context.toggle_mark();
+        context.clear_user_input();
+        select(&mut context, "a");
+        let selected_match = context.selected_match().unwrap();
+        assert!(context.is_marked(&selected_match));
+        context.toggle_mark();
+
+        assert_eq!(reprs(context.take_marked()), ["b", "c"]);
+        assert_eq!(context.marked_count(), 0);
+    }
+
+    #[test]
+    fn take_marked_defaults_to_selection() {
+        let mut context = context_with_choices(&["a", "b"]);
+        select(&mut context, "b");
+        assert_eq!(reprs(context.take_marked()), ["b"]);
+    }
+
+    fn running_session(name: &str) -> SessionEntry {
+        SessionEntry {
+            name: name.to_string(),
+            status: SessionStatus::Running,
+            tab_count: 1,
+            connected_clients: 0,
+            exited_for: None,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn session_picker_filters_sessions() {
+        let mut context = context_with_choices(&["src/nix-config"]);
+        let session_name = session_name("src/nix-config");
+        context.set_session_entries(
+            [
+                SessionEntry::exited("dotfiles".to_string(), Duration::from_secs(60)),
+                running_session("sync"),
+                running_session(&session_name),
+            ]
+            .into_iter(),
+        );
+
+        context.toggle_picker();
+        assert_eq!(context.choice_count(), 3);
+        // Running sessions are listed first.
+        assert_eq!(context.selected_session().unwrap().name, session_name);
+        assert_eq!(
+            context.selected_session().unwrap().path,
+            Some(PathBuf::from("src/nix-config"))
+        );
+
+        "dot".chars().for_each(|ch| _ = context.on_user_input(ch));
+        assert_eq!(context.match_count(), 1);
+        assert_eq!(context.selected_session().unwrap().name, "dotfiles");
+
+        // The user input is shared with the directory picker.
+        context.toggle_picker();
+        assert_eq!(context.match_count(), 0);
+    }
+
+    #[test]
+    fn session_selection_follows_session_updates() {
+        let mut context = FuzzySearchContext::default();
+        context.toggle_picker();
+        context.set_session_entries(["a", "c"].map(running_session).into_iter());
+        context.select_last();
+
+        context.set_session_entries(["a", "b", "c"].map(running_session).into_iter());
+        assert_eq!(context.selected_index(), 2);
+        assert_eq!(context.selected_session().unwrap().name, "c");
+    }
+
+    #[test]
+    fn colliding_session_names_are_disambiguated() {
+        let work = hash::collision_fallback("api", Path::new("work/api")).unwrap();
+        assert_ne!(work, "api");
+
+        for (first, second) in [("work/api", "perso/api"), ("perso/api", "work/api")] {
+            let mut context = FuzzySearchContext::default();
+            context.set_session_name_template(SessionNameTemplate::parse("{basename}").unwrap());
+            context.add_choices([first, "lib"].map(|c| PathBuf::from(c).into()).into_iter());
+            select(&mut context, first);
+            context.toggle_mark();
+            assert_eq!(context.session_name(Path::new(first)).unwrap(), "api");
+
+            // Names do not depend on the order directories are discovered in: the smallest path
+            // keeps the name.
+            context.add_choices(std::iter::once(PathBuf::from(second).into()));
+            assert_eq!(context.session_name(Path::new("perso/api")).unwrap(), "api");
+            assert_eq!(context.session_name(Path::new("work/api")).unwrap(), work);
+            assert_eq!(context.session_name(Path::new("lib")).unwrap(), "lib");
+            for (repr, session_name) in [("perso/api", "api"), ("work/api", work.as_str())] {
+                select(&mut context, repr);
+                assert_eq!(
+                    context.selected_match().unwrap().session_name(),
+                    Some(session_name)
+                );
+            }
+
+            // The selection and marks follow renamed entries.
+            let first_name = context.session_name(Path::new(first)).unwrap();
+            assert_eq!(
+                context.take_marked()[0].session_name(),
+                Some(first_name.as_str())
+            );
+
+            // The same holds within a batch.
+            let mut context = FuzzySearchContext::default();
+            context.set_session_name_template(SessionNameTemplate::parse("{basename}").unwrap());
+            context.add_choices([first, second].map(|c| PathBuf::from(c).into()).into_iter());
+            select(&mut context, "work/api");
+            assert_eq!(
+                context.selected_match().unwrap().session_name(),
+                Some(work.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_session_name_checks_alias_then_default_template() {
+        let mut context = FuzzySearchContext::default();
+        context.set_session_name_template(SessionNameTemplate::parse("{basename}").unwrap());
+        let path = Path::new("src/nix-config");
+        let default_name = session_name("src/nix-config");
+        context.set_sessions(
+            None,
+            ["nix-config", "dotfiles", default_name.as_str()]
+                .map(str::to_string)
+                .into(),
+            BTreeSet::new(),
+        );
+
+        assert_eq!(
+            context.legacy_session_name(path, Some("dotfiles")),
+            Some("dotfiles".to_string())
+        );
+        assert_eq!(
+            context.legacy_session_name(path, Some("gone")),
+            Some(default_name)
+        );
+        // The current name is not a legacy name.
+        context.set_session_name_template(SessionNameTemplate::default());
+        assert_eq!(context.legacy_session_name(path, None), None);
+
+        // Legacy names were neither sanitized nor truncated.
+        context.set_session_name_template(SessionNameTemplate::parse("{basename}").unwrap());
+        let path = Path::new("src/my project");
+        let legacy_name = format!("{}-my project", &session_name("src/my project")[..8]);
+        context.set_sessions(None, [legacy_name.clone()].into(), BTreeSet::new());
+        assert_eq!(context.legacy_session_name(path, None), Some(legacy_name));
+    }
+
+    #[test]
+    fn fresh_session_name_skips_existing_sessions() {
+        let mut context = FuzzySearchContext::default();
+        assert_eq!(context.fresh_session_name("foo"), "foo-2");
+
+        context.set_sessions(
+            Some("foo-2".to_string()),
+            ["foo".to_string(), "foo-2".to_string()].into(),
+            ["foo-3".to_string()].into(),
+        );
+        assert_eq!(context.fresh_session_name("foo"), "foo-4");
+    }
+
+    #[test]
+    fn frecency_orders_empty_query() {
+        let mut context = context_with_choices(&["a", "b", "c"]);
+        let mut frecency = FrecencyStore::default();
+        frecency.record_visit(PathBuf::from("/root/b"), frecency::now());
+
+        context.set_frecency(frecency, PathBuf::from("/root"));
+
+        let first_match = context.matches().next().unwrap().choice.upgrade().unwrap();
+        assert_eq!(first_match.repr(), "b");
+    }
+
+    #[test]
+    fn switching_matcher_refilters_choices() {
+        let mut context = context_with_choices(&["nix-config", "sync"]);
+        "nc".chars().for_each(|ch| _ = context.on_user_input(ch));
+        assert_eq!(context.match_count(), 2);
+
+        assert!(context.set_matcher_kind(MatcherKind::Exact).as_bool());
+        assert_eq!(context.match_count(), 1);
+        assert_eq!(context.selected_match().unwrap().repr(), "sync");
+
+        assert!(!context.set_matcher_kind(MatcherKind::Exact).as_bool());
+    }
+
+    #[test]
+    fn selection_falls_back_to_index_when_match_disappears() {
+        let mut context = context_with_choices(&["abc", "abd", "xyz"]);
+        select(&mut context, "xyz");
+
+        context.on_user_input('a');
 
-        // TODO: update self.selected_match
+        assert!(context.selected_index() < context.match_count());
+        assert_ne!(context.selected_match().unwrap().repr(), "xyz");
     }
 }
@@ -1,13 +1,24 @@
-use std::path::PathBuf;
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
 
 use bstr::ByteSlice;
 use data_encoding::HEXLOWER;
 use sha2::digest;
-use std::io;
+use std::{fs, io};
 use zellij_tile::prelude::*;
 
 const HASH_PREFIX_LENGTH: usize = 8;
 
+/// The session name template in use unless configured otherwise: a short hash of the path, followed
+/// by its basename.
+pub(crate) const DEFAULT_SESSION_NAME_TEMPLATE: &'static str = "{hash:8}-{basename}";
+
+/// The longest session name produced by a [SessionNameTemplate]. Zellij names the socket of a
+/// session after it, and socket paths are limited to about a hundred bytes.
+pub(crate) const MAX_SESSION_NAME_LENGTH: usize = 48;
+
 /// Reads through all data from the passed reader, and returns the resulting [Digest].
 /// The exact hash function used is left generic over all [Digest].
 fn hash<D: digest::Digest + io::Write>(mut r: impl io::Read) -> Result<digest::Output<D>> {
@@ -16,30 +27,316 @@ fn hash<D: digest::Digest + io::Write>(mut r: impl io::Read) -> Result<digest::O
     Ok(hasher.finalize())
 }
 
-/// Returns a unique and stable session identifier for the given input path.
-/// The identifier consists of 2 segments joined by a dash (`-`) character:
-///   - a short hash of the path (8 character prefix)
-///   - the basename of the path
-pub(crate) fn get_session_name(path: &PathBuf) -> Result<String> {
-    let fname = path
-        .file_name()
-        .ok_or_else(|| anyhow!("invalid path (ends with a dot?): {path:?}"))?
-        .to_str()
-        .ok_or_else(|| anyhow!("failed to decode path (invalid UTF-8?): {path:?}"))?;
-    let hashed_path = HEXLOWER.encode(
+/// Returns the hexadecimal SHA-1 digest of `path`.
+fn hash_path(path: &Path) -> Result<String> {
+    Ok(HEXLOWER.encode(
         hash::<sha1::Sha1>(
             path.to_str()
                 .ok_or_else(|| anyhow!("failed to decode path (invalid UTF-8?): {path:?}"))?
                 .as_bytes(),
         )?
         .as_bstr(),
-    );
+    ))
+}
 
+/// Returns the name sessions were given before templates were introduced: a short hash of `path`,
+/// followed by its basename. Unlike rendered names, it is neither sanitized nor truncated.
+pub(crate) fn legacy_session_name(path: &Path) -> Result<String> {
+    let basename = path
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid path (ends with a dot?): {path:?}"))?
+        .to_str()
+        .ok_or_else(|| anyhow!("failed to decode path (invalid UTF-8?): {path:?}"))?;
     Ok(format!(
-        "{}-{fname}",
-        hashed_path
-            .chars()
-            .take(HASH_PREFIX_LENGTH)
-            .collect::<String>()
+        "{}-{basename}",
+        &hash_path(path)?[..HASH_PREFIX_LENGTH]
     ))
 }
+
+/// A part of a [SessionNameTemplate].
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    /// `{basename}`: the last component of the path.
+    Basename,
+    /// `{parent}`: the last component of the parent of the path, if any.
+    Parent,
+    /// `{path}`: the whole path, relative to the plugin's CWD.
+    Path,
+    /// `{hash}` or `{hash:N}`: the first `N` characters of the SHA-1 digest of the path.
+    Hash(usize),
+    /// `{git_branch}`: the branch checked out in the directory, or the abbreviated commit if the
+    /// HEAD is detached.
+    GitBranch,
+}
+
+/// Derives session names from directories, e.g. `{parent}/{basename}` or `{basename}@{hash:6}`.
+///
+/// Rendered names are sanitized for Zellij, and truncated to [MAX_SESSION_NAME_LENGTH] characters,
+/// except with the default template, which renders the [legacy_session_name] so that sessions
+/// created before templates were introduced keep their name.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SessionNameTemplate {
+    segments: Vec<Segment>,
+}
+
+impl SessionNameTemplate {
+    /// Parses a template where `{placeholder}`s are replaced by properties of the directory.
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        let mut segments = vec![];
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow!("unclosed placeholder: {:?}", &rest[start..]))?
+                + start;
+            segments.push(match &rest[start + 1..end] {
+                "basename" => Segment::Basename,
+                "parent" => Segment::Parent,
+                "path" => Segment::Path,
+                "hash" => Segment::Hash(HASH_PREFIX_LENGTH),
+                "git_branch" => Segment::GitBranch,
+                placeholder => match placeholder.strip_prefix("hash:").map(str::parse::<usize>) {
+                    Some(Ok(length @ 1..=40)) => Segment::Hash(length),
+                    Some(_) => bail!("invalid hash length, expected 1 to 40: {{{placeholder}}}"),
+                    None => bail!("unknown placeholder: {{{placeholder}}}"),
+                },
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        if segments.is_empty() {
+            bail!("empty template");
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Returns the session name of `path`, relative to the plugin's CWD.
+    pub(crate) fn render(&self, path: &Path) -> Result<String> {
+        let git_branch = self
+            .segments
+            .contains(&Segment::GitBranch)
+            .then(|| read_git_branch(&PathBuf::from("/host").join(path)))
+            .flatten();
+        self.render_with_branch(path, git_branch.as_deref())
+    }
+
+    fn render_with_branch(&self, path: &Path, git_branch: Option<&str>) -> Result<String> {
+        if self.is_default() {
+            return legacy_session_name(path);
+        }
+        fn decode<'p>(component: Option<&'p OsStr>, path: &Path) -> Result<&'p str> {
+            component
+                .unwrap_or_default()
+                .to_str()
+                .ok_or_else(|| anyhow!("failed to decode path (invalid UTF-8?): {path:?}"))
+        }
+        let basename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid path (ends with a dot?): {path:?}"))?;
+        let hashed_path = hash_path(path)?;
+
+        let mut name = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => name.push_str(literal),
+                Segment::Basename => name.push_str(decode(Some(basename), path)?),
+                Segment::Parent => {
+                    name.push_str(decode(path.parent().and_then(Path::file_name), path)?)
+                }
+                Segment::Path => name.push_str(decode(Some(path.as_os_str()), path)?),
+                Segment::Hash(length) => name.push_str(&hashed_path[..*length]),
+                Segment::GitBranch => name.push_str(git_branch.unwrap_or_default()),
+            }
+        }
+
+        let name = sanitize(&name);
+        if name.is_empty() {
+            // E.g. `{git_branch}` outside of a repository.
+            return collision_fallback("", path);
+        }
+        let name = name
+            .chars()
+            .take(MAX_SESSION_NAME_LENGTH)
+            .collect::<String>();
+        Ok(name.trim_end_matches('-').to_string())
+    }
+
+    /// Whether this is the [DEFAULT_SESSION_NAME_TEMPLATE].
+    fn is_default(&self) -> bool {
+        matches!(
+            self.segments.as_slice(),
+            [Segment::Hash(HASH_PREFIX_LENGTH), Segment::Literal(separator), Segment::Basename]
+                if separator == "-"
+        )
+    }
+}
+
+impl Default for SessionNameTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_SESSION_NAME_TEMPLATE).expect("the default template is valid")
+    }
+}
+
+/// Disambiguates `name` when several directories render to it, by appending a short hash of
+/// `path`. Names are truncated to make room for the hash.
+pub(crate) fn collision_fallback(name: &str, path: &Path) -> Result<String> {
+    let hashed_path = &hash_path(path)?[..HASH_PREFIX_LENGTH];
+    if name.is_empty() {
+        return Ok(hashed_path.to_string());
+    }
+    let prefix = name
+        .chars()
+        .take(MAX_SESSION_NAME_LENGTH - HASH_PREFIX_LENGTH - 1)
+        .collect::<String>();
+    Ok(format!("{}-{hashed_path}", prefix.trim_end_matches('-')))
+}
+
+/// Replaces the characters that are not allowed, or awkward to type, in Zellij session names with
+/// dashes. In particular, Zellij rejects names containing slashes, and names starting with a dash
+/// would be parsed as options by its CLI.
+pub(crate) fn sanitize(name: &str) -> String {
+    let is_allowed =
+        |ch: char| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '@' | '+');
+    let mut sanitized = String::with_capacity(name.len());
+    for ch in name.chars() {
+        match is_allowed(ch) {
+            true => sanitized.push(ch),
+            // Collapse runs of replaced characters.
+            false if !sanitized.ends_with('-') => sanitized.push('-'),
+            false => (),
+        }
+    }
+    sanitized.trim_matches('-').to_string()
+}
+
+/// Returns the branch checked out in the repository at `dir`, or the abbreviated commit if the
+/// HEAD is detached. Linked worktrees, whose `.git` is a file pointing to the actual Git
+/// directory, are supported as long as that directory is visible to the plugin.
+fn read_git_branch(dir: &Path) -> Option<String> {
+    let dot_git = dir.join(".git");
+    let git_dir = match fs::read_to_string(&dot_git) {
+        Ok(content) => dir.join(content.strip_prefix("gitdir:")?.trim()),
+        Err(_) => dot_git,
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => Some(
+            reference
+                .trim()
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference.trim())
+                .to_string(),
+        ),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, path: &str, git_branch: Option<&str>) -> String {
+        SessionNameTemplate::parse(template)
+            .unwrap()
+            .render_with_branch(Path::new(path), git_branch)
+            .unwrap()
+    }
+
+    #[test]
+    fn default_template() {
+        assert_eq!(
+            SessionNameTemplate::default()
+                .render(Path::new("src/nix-config"))
+                .unwrap(),
+            format!(
+                "{}-nix-config",
+                &hash_path(Path::new("src/nix-config")).unwrap()[..8]
+            )
+        );
+    }
+
+    #[test]
+    fn default_template_renders_legacy_names() {
+        // The algorithm in use before templates were introduced.
+        fn legacy(path: &str) -> String {
+            let hashed_path =
+                HEXLOWER.encode(hash::<sha1::Sha1>(path.as_bytes()).unwrap().as_bstr());
+            let basename = Path::new(path).file_name().unwrap().to_str().unwrap();
+            format!("{}-{basename}", &hashed_path[..8])
+        }
+
+        for path in ["src/my project", &format!("src/{}", "a".repeat(60))] {
+            assert_eq!(
+                SessionNameTemplate::default()
+                    .render(Path::new(path))
+                    .unwrap(),
+                legacy(path)
+            );
+        }
+    }
+
+    #[test]
+    fn render_placeholders() {
+        let hashed_path = hash_path(Path::new("work/infra/api")).unwrap();
+        assert_eq!(
+            render("{basename}@{hash:6}", "work/infra/api", None),
+            format!("api@{}", &hashed_path[..6])
+        );
+        assert_eq!(
+            render("{parent}/{basename}", "work/infra/api", None),
+            "infra-api"
+        );
+        assert_eq!(
+            render(
+                "{path} ({git_branch})",
+                "work/infra/api",
+                Some("feat/login")
+            ),
+            "work-infra-api-feat-login"
+        );
+        // Empty placeholders are dropped along with their separators.
+        assert_eq!(render("{parent}/{basename}", "api", None), "api");
+    }
+
+    #[test]
+    fn render_falls_back_to_hash_when_empty() {
+        let hashed_path = hash_path(Path::new("api")).unwrap();
+        assert_eq!(render("{git_branch}", "api", None), &hashed_path[..8]);
+    }
+
+    #[test]
+    fn render_truncates_long_names() {
+        let name = render("{basename}", &"a".repeat(100), None);
+        assert_eq!(name.len(), MAX_SESSION_NAME_LENGTH);
+    }
+
+    #[test]
+    fn collision_fallback_is_deterministic() {
+        let path = Path::new("work/api");
+        let hashed_path = hash_path(path).unwrap();
+        assert_eq!(
+            collision_fallback("api", path).unwrap(),
+            format!("api-{}", &hashed_path[..8])
+        );
+        let fallback = collision_fallback(&"a".repeat(MAX_SESSION_NAME_LENGTH), path).unwrap();
+        assert_eq!(fallback.len(), MAX_SESSION_NAME_LENGTH);
+        assert!(fallback.ends_with(&hashed_path[..8]));
+    }
+
+    #[test]
+    fn parse_invalid_templates() {
+        assert!(SessionNameTemplate::parse("{basename").is_err());
+        assert!(SessionNameTemplate::parse("{branch}").is_err());
+        assert!(SessionNameTemplate::parse("{hash:0}").is_err());
+        assert!(SessionNameTemplate::parse("{hash:x}").is_err());
+        assert!(SessionNameTemplate::parse("").is_err());
+    }
+}
@@ -0,0 +1,24 @@
+/// Stores persisted as JSON files, e.g. visit statistics and session names.
+use anyhow::Context as _;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, io, path::Path};
+
+/// Loads the `name` store from `path`. A missing file yields an empty store.
+pub(crate) fn load<T: DeserializeOwned + Default>(path: &Path, name: &str) -> anyhow::Result<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("deserializing {name} store {path:?}")),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(error) => Err(error).with_context(|| format!("reading {name} store {path:?}")),
+    }
+}
+
+/// Writes the `name` store to `path`, creating parent directories as needed.
+pub(crate) fn save<T: Serialize>(store: &T, path: &Path, name: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating {name} store directory {parent:?}"))?;
+    }
+    fs::write(path, serde_json::to_string(store)?)
+        .with_context(|| format!("writing {name} store {path:?}"))
+}
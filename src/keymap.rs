@@ -0,0 +1,534 @@
+/// Maps keys to named plugin actions.
+use crate::line_editor::LineEdit;
+
+use itertools::Itertools;
+use std::{collections::BTreeSet, str::FromStr};
+use zellij_tile::prelude::{BareKey, KeyModifier, KeyWithModifier};
+
+/// A named action that can be bound to keys.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Action {
+    /// Switches to the session of the selected entry.
+    Select,
+    SelectUp,
+    SelectDown,
+    SelectPageUp,
+    SelectPageDown,
+    SelectFirst,
+    SelectLast,
+    /// Clears the user input, or closes the plugin if it is already empty.
+    Clear,
+    Quit,
+    /// Switches to the next available matcher.
+    SwitchMatcher,
+    /// Edits the user input.
+    Edit(LineEdit),
+    /// Switches to [InputMode::Insert], focusing the prompt.
+    EnterInsertMode,
+    /// Switches to [InputMode::Normal].
+    EnterNormalMode,
+    /// Opens the selected entry somewhere other than its own session.
+    Open(OpenTarget),
+    /// Copies the path of the selected entry to the clipboard.
+    CopyPath,
+    /// Marks or unmarks the selected entry for a batch action.
+    ToggleMark,
+    /// Opens all marked entries at once, or the selected entry if none is marked.
+    OpenMarked(BatchTarget),
+    /// Switches between the directory and session pickers.
+    TogglePicker,
+    /// Kills the selected session, after confirmation.
+    KillSession,
+    /// Renames the selected session, which must be the current one.
+    RenameSession,
+    /// Detaches from the selected session, which must be the current one.
+    DetachSession,
+}
+
+/// How to open marked entries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum BatchTarget {
+    /// Creates the session of each entry in the background, without switching to any of them.
+    BackgroundSessions,
+    /// Creates a single session, with a tab for each entry.
+    Tabs,
+}
+
+/// Where to open the selected entry, as an alternative to [Action::Select].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum OpenTarget {
+    /// Switches to the session of the entry only if it already exists (running or exited).
+    ExistingSession,
+    /// Creates a new session for the entry, even if one already exists.
+    FreshSession,
+    /// Opens a new tab in the current session.
+    Tab,
+    /// Opens a floating pane in the current tab.
+    FloatingPane,
+    /// Opens a tiled pane in the current tab.
+    TiledPane,
+}
+
+impl Action {
+    /// All actions, along with their name as used in `bind_<name>` configuration options.
+    const NAMES: [(Action, &str); 35] = [
+        (Action::Select, "select"),
+        (Action::SelectUp, "up"),
+        (Action::SelectDown, "down"),
+        (Action::SelectPageUp, "page_up"),
+        (Action::SelectPageDown, "page_down"),
+        (Action::SelectFirst, "first"),
+        (Action::SelectLast, "last"),
+        (Action::Clear, "clear"),
+        (Action::Quit, "quit"),
+        (Action::SwitchMatcher, "switch_matcher"),
+        (Action::Edit(LineEdit::MoveLeft), "cursor_left"),
+        (Action::Edit(LineEdit::MoveRight), "cursor_right"),
+        (Action::Edit(LineEdit::MoveStart), "cursor_start"),
+        (Action::Edit(LineEdit::MoveEnd), "cursor_end"),
+        (Action::Edit(LineEdit::MoveWordBackward), "word_backward"),
+        (Action::Edit(LineEdit::MoveWordForward), "word_forward"),
+        (Action::Edit(LineEdit::DeleteBackward), "delete_backward"),
+        (Action::Edit(LineEdit::DeleteForward), "delete_forward"),
+        (
+            Action::Edit(LineEdit::DeleteWordBackward),
+            "delete_word_backward",
+        ),
+        (Action::Edit(LineEdit::DeleteToStart), "delete_to_start"),
+        (Action::EnterInsertMode, "insert_mode"),
+        (Action::EnterNormalMode, "normal_mode"),
+        (Action::Open(OpenTarget::ExistingSession), "switch_only"),
+        (Action::Open(OpenTarget::FreshSession), "fresh_session"),
+        (Action::Open(OpenTarget::Tab), "open_tab"),
+        (Action::Open(OpenTarget::FloatingPane), "open_floating_pane"),
+        (Action::Open(OpenTarget::TiledPane), "open_tiled_pane"),
+        (Action::CopyPath, "copy_path"),
+        (Action::ToggleMark, "toggle_mark"),
+        (
+            Action::OpenMarked(BatchTarget::BackgroundSessions),
+            "open_marked_sessions",
+        ),
+        (Action::OpenMarked(BatchTarget::Tabs), "open_marked_tabs"),
+        (Action::TogglePicker, "toggle_sessions"),
+        (Action::KillSession, "kill_session"),
+        (Action::RenameSession, "rename_session"),
+        (Action::DetachSession, "detach_session"),
+    ];
+
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        Self::NAMES
+            .into_iter()
+            .find_map(|(action, action_name)| (action_name == name).then_some(action))
+    }
+}
+
+/// The mode of modal keymaps, which decides how keys are interpreted.
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum InputMode {
+    /// Keys that aren't bound to any action are inserted in the user input.
+    #[default]
+    Insert,
+    /// Keys that aren't bound to any action are ignored.
+    Normal,
+}
+
+impl InputMode {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            InputMode::Insert => "INSERT",
+            InputMode::Normal => "NORMAL",
+        }
+    }
+}
+
+/// A built-in set of key bindings, which can be further customized with `bind_<action>` options.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum KeymapPreset {
+    /// Arrow keys and readline-style line editing.
+    #[default]
+    Default,
+    /// [KeymapPreset::Default], plus `Ctrl n`/`Ctrl p` and `Ctrl j`/`Ctrl k` to move the
+    /// selection.
+    Readline,
+    /// Modal bindings: [KeymapPreset::Default] in [InputMode::Insert], which `Esc` leaves for
+    /// [InputMode::Normal], where `j`/`k`, `gg`/`G` move the selection, `/` focuses the prompt and
+    /// `q` quits.
+    Vim,
+}
+
+impl KeymapPreset {
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(KeymapPreset::Default),
+            "readline" => Some(KeymapPreset::Readline),
+            "vim" => Some(KeymapPreset::Vim),
+            _ => None,
+        }
+    }
+}
+
+/// The keys bound to each action in [InputMode::Insert] unless configured otherwise.
+const DEFAULT_BINDINGS: [(Action, &[&str]); 33] = [
+    (Action::Select, &["Enter"]),
+    (Action::SelectUp, &["Up"]),
+    (Action::SelectDown, &["Down"]),
+    (Action::SelectPageUp, &["PageUp"]),
+    (Action::SelectPageDown, &["PageDown"]),
+    (Action::SelectFirst, &["Ctrl Home"]),
+    (Action::SelectLast, &["Ctrl End"]),
+    (Action::Clear, &["Esc"]),
+    (Action::Quit, &["Ctrl c"]),
+    (Action::SwitchMatcher, &["Ctrl t"]),
+    (Action::Edit(LineEdit::MoveLeft), &["Left"]),
+    (Action::Edit(LineEdit::MoveRight), &["Right"]),
+    (Action::Edit(LineEdit::MoveStart), &["Home", "Ctrl a"]),
+    (Action::Edit(LineEdit::MoveEnd), &["End", "Ctrl e"]),
+    (Action::Edit(LineEdit::MoveWordBackward), &["Alt b"]),
+    (Action::Edit(LineEdit::MoveWordForward), &["Alt f"]),
+    (Action::Edit(LineEdit::DeleteBackward), &["Backspace"]),
+    (Action::Edit(LineEdit::DeleteForward), &["Delete"]),
+    (Action::Edit(LineEdit::DeleteWordBackward), &["Ctrl w"]),
+    (Action::Edit(LineEdit::DeleteToStart), &["Ctrl u"]),
+    (Action::Open(OpenTarget::ExistingSession), &["Alt s"]),
+    (Action::Open(OpenTarget::FreshSession), &["Alt n"]),
+    (Action::Open(OpenTarget::Tab), &["Alt t"]),
+    (Action::Open(OpenTarget::FloatingPane), &["Alt w"]),
+    (Action::Open(OpenTarget::TiledPane), &["Alt p"]),
+    (Action::CopyPath, &["Alt y"]),
+    (Action::ToggleMark, &["Tab"]),
+    (
+        Action::OpenMarked(BatchTarget::BackgroundSessions),
+        &["Alt Enter"],
+    ),
+    (Action::OpenMarked(BatchTarget::Tabs), &["Alt o"]),
+    (Action::TogglePicker, &["Ctrl l"]),
+    (Action::KillSession, &["Ctrl x"]),
+    (Action::RenameSession, &["Ctrl r"]),
+    (Action::DetachSession, &["Ctrl d"]),
+];
+
+/// Added to [DEFAULT_BINDINGS] by [KeymapPreset::Readline].
+const READLINE_BINDINGS: [(Action, &[&str]); 2] = [
+    (Action::SelectUp, &["Up", "Ctrl p", "Ctrl k"]),
+    (Action::SelectDown, &["Down", "Ctrl n", "Ctrl j"]),
+];
+
+/// Replaces [DEFAULT_BINDINGS] in [InputMode::Insert] for [KeymapPreset::Vim].
+const VIM_INSERT_BINDINGS: [(Action, &[&str]); 2] =
+    [(Action::Clear, &[]), (Action::EnterNormalMode, &["Esc"])];
+
+/// The bindings of [InputMode::Normal] for [KeymapPreset::Vim].
+const VIM_NORMAL_BINDINGS: [(Action, &[&str]); 23] = [
+    (Action::Select, &["Enter"]),
+    (Action::SelectUp, &["k", "Up"]),
+    (Action::SelectDown, &["j", "Down"]),
+    (Action::SelectPageUp, &["Ctrl u", "PageUp"]),
+    (Action::SelectPageDown, &["Ctrl d", "PageDown"]),
+    (Action::SelectFirst, &["g g"]),
+    (Action::SelectLast, &["G"]),
+    (Action::Quit, &["q", "Ctrl c"]),
+    (Action::SwitchMatcher, &["Ctrl t"]),
+    (Action::EnterInsertMode, &["/", "i"]),
+    (Action::Open(OpenTarget::ExistingSession), &["s"]),
+    (Action::Open(OpenTarget::FreshSession), &["n"]),
+    (Action::Open(OpenTarget::Tab), &["t"]),
+    (Action::Open(OpenTarget::FloatingPane), &["w"]),
+    (Action::Open(OpenTarget::TiledPane), &["p"]),
+    (Action::CopyPath, &["y"]),
+    (Action::ToggleMark, &["m", "Tab"]),
+    (Action::OpenMarked(BatchTarget::BackgroundSessions), &["b"]),
+    (Action::OpenMarked(BatchTarget::Tabs), &["o"]),
+    (Action::TogglePicker, &["Ctrl l"]),
+    (Action::KillSession, &["x"]),
+    (Action::RenameSession, &["r"]),
+    (Action::DetachSession, &["D"]),
+];
+
+/// A key, or a sequence of keys pressed one after the other (e.g. `g g`).
+pub(crate) type KeySequence = Vec<KeyWithModifier>;
+
+#[derive(Debug)]
+struct Binding {
+    mode: InputMode,
+    keys: KeySequence,
+    action: Action,
+}
+
+/// How the keys pressed so far resolve against a [Keymap].
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum KeyResolution {
+    /// The keys are bound to an action.
+    Action(Action),
+    /// The keys are the beginning of at least one longer sequence.
+    Pending,
+    /// The keys aren't bound to any action.
+    Unbound,
+}
+
+/// The active key bindings.
+#[derive(Debug)]
+pub(crate) struct Keymap {
+    // NOTE: `KeyWithModifier`'s `Ord` implementation is not consistent with its `PartialEq`
+    // implementation (e.g. `Shift a` == `A`), so it can't be used as a map key.
+    bindings: Vec<Binding>,
+    /// Whether [InputMode::Normal] is available.
+    is_modal: bool,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(KeymapPreset::default())
+    }
+}
+
+impl Keymap {
+    pub(crate) fn new(preset: KeymapPreset) -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            is_modal: preset == KeymapPreset::Vim,
+        };
+        keymap.bind_defaults(InputMode::Insert, &DEFAULT_BINDINGS);
+        match preset {
+            KeymapPreset::Default => (),
+            KeymapPreset::Readline => keymap.bind_defaults(InputMode::Insert, &READLINE_BINDINGS),
+            KeymapPreset::Vim => {
+                keymap.bind_defaults(InputMode::Insert, &VIM_INSERT_BINDINGS);
+                keymap.bind_defaults(InputMode::Normal, &VIM_NORMAL_BINDINGS);
+            }
+        }
+
+        keymap
+    }
+
+    fn bind_defaults(&mut self, mode: InputMode, bindings: &[(Action, &[&str])]) {
+        for (action, keys) in bindings {
+            let keys = keys
+                .iter()
+                .map(|keys| parse_key_sequence(keys).expect("valid default key binding"))
+                .collect();
+            self.bind(mode, *action, keys);
+        }
+    }
+
+    pub(crate) fn is_modal(&self) -> bool {
+        self.is_modal
+    }
+
+    /// Binds `action` to `keys` in `mode`, replacing the keys previously bound to `action` as well
+    /// as the previous bindings of `keys`.
+    pub(crate) fn bind(&mut self, mode: InputMode, action: Action, keys: Vec<KeySequence>) {
+        self.bindings.retain(|binding| {
+            binding.mode != mode || (binding.action != action && !keys.contains(&binding.keys))
+        });
+        self.bindings
+            .extend(keys.into_iter().map(|keys| Binding { mode, keys, action }));
+    }
+
+    /// Resolves the sequence of `keys` pressed so far in `mode`.
+    pub(crate) fn resolve(&self, mode: InputMode, keys: &[KeyWithModifier]) -> KeyResolution {
+        let mut resolution = KeyResolution::Unbound;
+        for binding in self.bindings.iter().filter(|binding| binding.mode == mode) {
+            if binding.keys == keys {
+                return KeyResolution::Action(binding.action);
+            }
+            if binding.keys.starts_with(keys) {
+                resolution = KeyResolution::Pending;
+            }
+        }
+
+        resolution
+    }
+
+    /// The keys bound to `action` in `mode`, in configuration order.
+    pub(crate) fn keys(
+        &self,
+        mode: InputMode,
+        action: Action,
+    ) -> impl Iterator<Item = &KeySequence> {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.mode == mode && binding.action == action)
+            .map(|binding| &binding.keys)
+    }
+}
+
+/// Parses a comma-separated list of key sequences, e.g. `Down, Ctrl n, g g`.
+pub(crate) fn parse_keys(spec: &str) -> Result<Vec<KeySequence>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|keys| !keys.is_empty())
+        .map(parse_key_sequence)
+        .collect()
+}
+
+/// Parses a space-separated sequence of keys, where modifiers apply to the key that follows them,
+/// e.g. `Ctrl x Ctrl e`.
+fn parse_key_sequence(spec: &str) -> Result<KeySequence, String> {
+    let mut keys = Vec::new();
+    let mut key_modifiers = BTreeSet::new();
+    for token in spec.split_ascii_whitespace() {
+        if let Ok(modifier) = KeyModifier::from_str(token) {
+            key_modifiers.insert(modifier);
+            continue;
+        }
+        let bare_key =
+            BareKey::from_str(token).map_err(|error| format!("invalid key `{spec}`: {error}"))?;
+        keys.push(KeyWithModifier::new_with_modifiers(
+            bare_key,
+            std::mem::take(&mut key_modifiers),
+        ));
+    }
+    if !key_modifiers.is_empty() {
+        return Err(format!("invalid key `{spec}`: missing key after modifiers"));
+    }
+
+    Ok(keys)
+}
+
+/// Formats `keys` for display, e.g. `Ctrl x Ctrl e`.
+pub(crate) fn display_key_sequence(keys: &KeySequence) -> String {
+    keys.iter().map(ToString::to_string).join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(spec: &str) -> KeyWithModifier {
+        KeyWithModifier::from_str(spec).unwrap()
+    }
+
+    fn resolve(keymap: &Keymap, mode: InputMode, keys: &str) -> KeyResolution {
+        keymap.resolve(mode, &parse_key_sequence(keys).unwrap())
+    }
+
+    #[test]
+    fn parse_action_names() {
+        for (action, name) in Action::NAMES {
+            assert_eq!(Action::parse(name), Some(action));
+        }
+        assert_eq!(Action::parse("unknown"), None);
+    }
+
+    #[test]
+    fn default_bindings() {
+        let keymap = Keymap::default();
+        let action = |key: KeyWithModifier| keymap.resolve(InputMode::Insert, &[key]);
+
+        assert_eq!(action(key("Enter")), KeyResolution::Action(Action::Select));
+        assert_eq!(
+            action(KeyWithModifier::new(BareKey::Enter).with_alt_modifier()),
+            KeyResolution::Action(Action::OpenMarked(BatchTarget::BackgroundSessions))
+        );
+        assert_eq!(
+            action(KeyWithModifier::new(BareKey::Char('a')).with_ctrl_modifier()),
+            KeyResolution::Action(Action::Edit(LineEdit::MoveStart))
+        );
+        assert_eq!(
+            action(KeyWithModifier::new_with_modifiers(
+                BareKey::Char('a'),
+                [KeyModifier::Ctrl, KeyModifier::Alt].into()
+            )),
+            KeyResolution::Unbound
+        );
+        assert_eq!(action(key("a")), KeyResolution::Unbound);
+        assert!(!keymap.is_modal());
+    }
+
+    #[test]
+    fn bind_replaces_previous_bindings() {
+        let mut keymap = Keymap::default();
+        keymap.bind(
+            InputMode::Insert,
+            Action::SelectDown,
+            parse_keys("Ctrl n, Ctrl e").unwrap(),
+        );
+
+        assert_eq!(
+            resolve(&keymap, InputMode::Insert, "Down"),
+            KeyResolution::Unbound
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Insert, "Ctrl n"),
+            KeyResolution::Action(Action::SelectDown)
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Insert, "Ctrl e"),
+            KeyResolution::Action(Action::SelectDown)
+        );
+        assert_eq!(
+            keymap
+                .keys(InputMode::Insert, Action::Edit(LineEdit::MoveEnd))
+                .collect::<Vec<_>>(),
+            vec![&vec![key("End")]]
+        );
+    }
+
+    #[test]
+    fn parse_key_sequences() {
+        assert_eq!(
+            parse_keys("Ctrl n, g g, Ctrl x Ctrl e"),
+            Ok(vec![
+                vec![key("Ctrl n")],
+                vec![key("g"), key("g")],
+                vec![key("Ctrl x"), key("Ctrl e")],
+            ])
+        );
+        assert!(parse_keys("Ctrl n, Hyper x").is_err());
+        assert!(parse_keys("Ctrl").is_err());
+        assert_eq!(parse_keys(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn readline_preset() {
+        let keymap = Keymap::new(KeymapPreset::Readline);
+
+        for keys in ["Down", "Ctrl n", "Ctrl j"] {
+            assert_eq!(
+                resolve(&keymap, InputMode::Insert, keys),
+                KeyResolution::Action(Action::SelectDown)
+            );
+        }
+        for keys in ["Up", "Ctrl p", "Ctrl k"] {
+            assert_eq!(
+                resolve(&keymap, InputMode::Insert, keys),
+                KeyResolution::Action(Action::SelectUp)
+            );
+        }
+    }
+
+    #[test]
+    fn vim_preset() {
+        let keymap = Keymap::new(KeymapPreset::Vim);
+        assert!(keymap.is_modal());
+
+        assert_eq!(
+            resolve(&keymap, InputMode::Insert, "Esc"),
+            KeyResolution::Action(Action::EnterNormalMode)
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Insert, "j"),
+            KeyResolution::Unbound
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Normal, "j"),
+            KeyResolution::Action(Action::SelectDown)
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Normal, "g"),
+            KeyResolution::Pending
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Normal, "g g"),
+            KeyResolution::Action(Action::SelectFirst)
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Normal, "G"),
+            KeyResolution::Action(Action::SelectLast)
+        );
+        assert_eq!(
+            resolve(&keymap, InputMode::Normal, "/"),
+            KeyResolution::Action(Action::EnterInsertMode)
+        );
+    }
+}
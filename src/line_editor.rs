@@ -0,0 +1,249 @@
+/// An editing command applied to a [LineEditor].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum LineEdit {
+    /// Moves the cursor one character to the left.
+    MoveLeft,
+    /// Moves the cursor one character to the right.
+    MoveRight,
+    /// Moves the cursor to the beginning of the line.
+    MoveStart,
+    /// Moves the cursor to the end of the line.
+    MoveEnd,
+    /// Moves the cursor to the beginning of the current or previous word.
+    MoveWordBackward,
+    /// Moves the cursor to the end of the current or next word.
+    MoveWordForward,
+    /// Deletes the character before the cursor.
+    DeleteBackward,
+    /// Deletes the character under the cursor.
+    DeleteForward,
+    /// Deletes the whitespace-delimited word before the cursor.
+    DeleteWordBackward,
+    /// Deletes everything before the cursor.
+    DeleteToStart,
+}
+
+/// The outcome of a [LineEdit].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum LineEditOutcome {
+    /// Nothing changed, e.g. moving left at the beginning of the line.
+    Unchanged,
+    /// Only the cursor moved.
+    Moved,
+    /// The content of the line changed.
+    Edited,
+}
+
+/// A single-line text editor, backing the search prompt.
+#[derive(Debug, Default)]
+pub(crate) struct LineEditor {
+    line: String,
+    /// The byte offset of the cursor in [line]. Always on a character boundary.
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.line
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.line.is_empty()
+    }
+
+    /// The byte offset of the cursor.
+    pub(crate) fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub(crate) fn insert(&mut self, ch: char) {
+        self.line.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+    }
+
+    pub(crate) fn apply(&mut self, edit: LineEdit) -> LineEditOutcome {
+        match edit {
+            LineEdit::MoveLeft => self.move_to(self.prev_char_boundary()),
+            LineEdit::MoveRight => self.move_to(self.next_char_boundary()),
+            LineEdit::MoveStart => self.move_to(0),
+            LineEdit::MoveEnd => self.move_to(self.line.len()),
+            LineEdit::MoveWordBackward => {
+                self.move_to(self.word_start_before(|ch| !ch.is_alphanumeric()))
+            }
+            LineEdit::MoveWordForward => self.move_to(self.word_end_after()),
+            LineEdit::DeleteBackward => self.delete_to(self.prev_char_boundary()),
+            LineEdit::DeleteForward => self.delete_to(self.next_char_boundary()),
+            LineEdit::DeleteWordBackward => {
+                self.delete_to(self.word_start_before(char::is_whitespace))
+            }
+            LineEdit::DeleteToStart => self.delete_to(0),
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize) -> LineEditOutcome {
+        if cursor == self.cursor {
+            return LineEditOutcome::Unchanged;
+        }
+        self.cursor = cursor;
+        LineEditOutcome::Moved
+    }
+
+    /// Deletes the characters between the cursor and `position`, in either direction.
+    fn delete_to(&mut self, position: usize) -> LineEditOutcome {
+        if position == self.cursor {
+            return LineEditOutcome::Unchanged;
+        }
+        let range = position.min(self.cursor)..position.max(self.cursor);
+        self.cursor = range.start;
+        self.line.replace_range(range, "");
+        LineEditOutcome::Edited
+    }
+
+    fn prev_char_boundary(&self) -> usize {
+        self.line[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_char_boundary(&self) -> usize {
+        self.line[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |ch| self.cursor + ch.len_utf8())
+    }
+
+    /// The beginning of the word before the cursor, skipping separators right before the cursor.
+    fn word_start_before(&self, is_separator: impl Fn(char) -> bool) -> usize {
+        let before = &self.line[..self.cursor];
+        let word_end = before.trim_end_matches(&is_separator).len();
+        before[..word_end]
+            .char_indices()
+            .rev()
+            .find(|&(_, ch)| is_separator(ch))
+            .map_or(0, |(index, ch)| index + ch.len_utf8())
+    }
+
+    /// The end of the alphanumeric word after the cursor, skipping separators right after the
+    /// cursor.
+    fn word_end_after(&self) -> usize {
+        let after = &self.line[self.cursor..];
+        let word_start = after.len()
+            - after
+                .trim_start_matches(|ch: char| !ch.is_alphanumeric())
+                .len();
+        after[word_start..]
+            .char_indices()
+            .find(|&(_, ch)| !ch.is_alphanumeric())
+            .map_or(self.line.len(), |(index, _)| {
+                self.cursor + word_start + index
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an editor from `line`, where `|` marks the cursor position.
+    fn editor(line: &str) -> LineEditor {
+        let cursor = line.find('|').expect("cursor marker");
+        LineEditor {
+            line: line.replacen('|', "", 1),
+            cursor,
+        }
+    }
+
+    fn render(editor: &LineEditor) -> String {
+        let (before, after) = editor.line.split_at(editor.cursor);
+        format!("{before}|{after}")
+    }
+
+    fn apply(line: &str, edit: LineEdit) -> (String, LineEditOutcome) {
+        let mut editor = editor(line);
+        let outcome = editor.apply(edit);
+        (render(&editor), outcome)
+    }
+
+    #[test]
+    fn insert_at_cursor() {
+        let mut editor = editor("ni|-config");
+        editor.insert('x');
+        editor.insert('é');
+        assert_eq!(render(&editor), "nixé|-config");
+    }
+
+    #[test]
+    fn char_motions_handle_multibyte_characters() {
+        use LineEdit::*;
+        use LineEditOutcome::*;
+
+        assert_eq!(apply("café|", MoveLeft), ("caf|é".to_string(), Moved));
+        assert_eq!(apply("caf|é", MoveRight), ("café|".to_string(), Moved));
+        assert_eq!(apply("café|", MoveRight), ("café|".to_string(), Unchanged));
+        assert_eq!(apply("|café", MoveLeft), ("|café".to_string(), Unchanged));
+        assert_eq!(apply("ca|fé", MoveStart), ("|café".to_string(), Moved));
+        assert_eq!(apply("ca|fé", MoveEnd), ("café|".to_string(), Moved));
+        assert_eq!(apply("café|", DeleteBackward), ("caf|".to_string(), Edited));
+        assert_eq!(apply("caf|é", DeleteForward), ("caf|".to_string(), Edited));
+        assert_eq!(
+            apply("café|", DeleteForward),
+            ("café|".to_string(), Unchanged)
+        );
+    }
+
+    #[test]
+    fn word_motions() {
+        use LineEdit::*;
+        use LineEditOutcome::*;
+
+        assert_eq!(
+            apply("src/nix-config|", MoveWordBackward),
+            ("src/nix-|config".to_string(), Moved)
+        );
+        assert_eq!(
+            apply("src/nix-|config", MoveWordBackward),
+            ("src/|nix-config".to_string(), Moved)
+        );
+        assert_eq!(
+            apply("|src/nix-config", MoveWordForward),
+            ("src|/nix-config".to_string(), Moved)
+        );
+        assert_eq!(
+            apply("src|/nix-config", MoveWordForward),
+            ("src/nix|-config".to_string(), Moved)
+        );
+        assert_eq!(
+            apply("src/nix-config|", MoveWordForward),
+            ("src/nix-config|".to_string(), Unchanged)
+        );
+    }
+
+    #[test]
+    fn word_and_line_deletion() {
+        use LineEdit::*;
+        use LineEditOutcome::*;
+
+        assert_eq!(
+            apply("infra !^archive/ |-api$", DeleteWordBackward),
+            ("infra |-api$".to_string(), Edited)
+        );
+        assert_eq!(
+            apply("infra|", DeleteWordBackward),
+            ("|".to_string(), Edited)
+        );
+        assert_eq!(
+            apply("infra -a|pi$", DeleteToStart),
+            ("|pi$".to_string(), Edited)
+        );
+        assert_eq!(
+            apply("|infra", DeleteToStart),
+            ("|infra".to_string(), Unchanged)
+        );
+    }
+}
@@ -7,9 +7,14 @@
 /// of systematically creating new ones.
 use zellij_tile::prelude::*;
 
+mod bootstrap;
 mod core;
+mod frecency;
 mod fuzzy_search_context;
 mod hash;
+mod json_store;
+mod keymap;
+mod line_editor;
 #[cfg(feature = "zellij_run_command_api")]
 mod marshall_command;
 #[cfg(not(feature = "zellij_fallback_fs_api"))]
@@ -17,6 +22,7 @@ mod marshall_plugin;
 mod matcher;
 mod plugin;
 mod protocol;
+mod session_alias;
 mod ui;
 #[cfg(not(feature = "zellij_fallback_fs_api"))]
 mod workers;
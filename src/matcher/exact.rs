@@ -0,0 +1,40 @@
+/// Substring matcher.
+use super::{chars_eq, is_smart_case_insensitive, Matcher};
+
+/// Added to the score of matches starting at the beginning of a word.
+const WORD_BOUNDARY_BONUS: i64 = 16;
+
+/// Matches choices containing the user input verbatim. Matching ignores case unless the input
+/// contains uppercase characters.
+pub(super) struct ExactMatcher;
+
+impl Matcher for ExactMatcher {
+    fn match_indices(&self, repr: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        let ignore_case = is_smart_case_insensitive(input);
+        let repr = repr.chars().collect::<Vec<_>>();
+        let input = input.chars().collect::<Vec<_>>();
+
+        // Favor the first match starting at a word boundary, then the first match.
+        let start = (0..=repr.len().checked_sub(input.len())?)
+            .filter(|&start| {
+                input
+                    .iter()
+                    .zip(&repr[start..])
+                    .all(|(&lhs, &rhs)| chars_eq(lhs, rhs, ignore_case))
+            })
+            .min_by_key(|&start| !is_word_start(&repr, start))?;
+
+        // Shorter choices are tighter matches.
+        let score = if is_word_start(&repr, start) {
+            WORD_BOUNDARY_BONUS
+        } else {
+            0
+        } - repr.len() as i64;
+
+        Some((score, (start..start + input.len()).collect()))
+    }
+}
+
+fn is_word_start(repr: &[char], index: usize) -> bool {
+    index == 0 || !repr[index - 1].is_alphanumeric()
+}
@@ -0,0 +1,36 @@
+/// Fuzzy matchers provided by the `fuzzy-matcher` crate.
+use super::Matcher;
+
+use fuzzy_matcher::{clangd, skim::SkimMatcherV2, FuzzyMatcher as _};
+
+pub(super) struct SkimMatcher(SkimMatcherV2);
+
+impl Default for SkimMatcher {
+    fn default() -> Self {
+        Self(SkimMatcherV2::default().use_cache(true))
+    }
+}
+
+impl Matcher for SkimMatcher {
+    fn match_indices(&self, repr: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        self.0.fuzzy_indices(repr, input)
+    }
+}
+
+pub(super) struct ClangdMatcher(clangd::ClangdMatcher);
+
+impl Default for ClangdMatcher {
+    fn default() -> Self {
+        Self(
+            clangd::ClangdMatcher::default()
+                .smart_case()
+                .use_cache(true),
+        )
+    }
+}
+
+impl Matcher for ClangdMatcher {
+    fn match_indices(&self, repr: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        self.0.fuzzy_indices(repr, input)
+    }
+}
@@ -0,0 +1,198 @@
+use std::rc::Weak;
+
+mod exact;
+mod fuzzy;
+mod pattern;
+mod query;
+mod segment;
+
+pub(crate) use query::Query;
+
+/// A trait allowing arbitrary data to be matched against the user input.
+pub(super) trait Choice {
+    fn repr(&self) -> &str;
+}
+
+/// A match against the user input.
+/// Returned by the matcher and used by the renderer to display the list of matches.
+pub(super) struct Match<C: Choice> {
+    /// A weak reference to the entry.
+    // TODO: Does it make sense to keep a `Weak<C>` here? We're only using `Rc<C>` to keep multiple
+    // references on the source-of-truth instances stored in the `FuzzyFinderContext`, so juggling
+    // between `Weak` and `Rc` seems unnecessary since we expect `Weak`s to always be promotable to
+    // `Rc`s.
+    pub choice: Weak<C>,
+
+    /// The list of indices in [Choice::repr()] that matched against the user input.
+    /// Used by the renderer to highlight matches.
+    pub indices: Vec<usize>,
+}
+
+/// A strategy to match the [Choice::repr()] of choices against the user input.
+pub(super) trait Matcher {
+    /// Matches `repr` against `input`. Returns the score of the match (higher is better) along with
+    /// the character indices in `repr` that matched, or `None` if `repr` does not match.
+    fn match_indices(&self, repr: &str, input: &str) -> Option<(i64, Vec<usize>)>;
+}
+
+/// The available [Matcher] implementations.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum MatcherKind {
+    /// Fuzzy matching, as implemented by skim.
+    #[default]
+    Skim,
+    /// Fuzzy matching, as implemented by clangd's code completion.
+    Clangd,
+    /// Case-insensitive substring matching, unless the input contains uppercase characters.
+    Exact,
+    /// Each `/`-separated part of the input matches the beginning of a path segment, in order.
+    Prefix,
+    /// The input is a regular expression.
+    Regex,
+}
+
+impl MatcherKind {
+    /// All matchers, in the order they are cycled through at runtime.
+    const ALL: [MatcherKind; 5] = [
+        MatcherKind::Skim,
+        MatcherKind::Clangd,
+        MatcherKind::Exact,
+        MatcherKind::Prefix,
+        MatcherKind::Regex,
+    ];
+
+    /// Parses the name of a matcher, as used in the plugin configuration.
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            MatcherKind::Skim => "skim",
+            MatcherKind::Clangd => "clangd",
+            MatcherKind::Exact => "exact",
+            MatcherKind::Prefix => "prefix",
+            MatcherKind::Regex => "regex",
+        }
+    }
+
+    /// The matcher following this one, wrapping around.
+    pub(crate) fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|kind| kind == self)
+            .unwrap_or_default();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Parses `input` into a [Query]. The extended search syntax is not available with the `regex`
+    /// matcher, whose own syntax conflicts with it.
+    pub(crate) fn parse_query(&self, input: &str) -> Query {
+        match self {
+            MatcherKind::Regex => Query::verbatim(input),
+            _ => Query::parse(input),
+        }
+    }
+
+    pub(super) fn matcher(&self) -> Box<dyn Matcher> {
+        match self {
+            MatcherKind::Skim => Box::new(fuzzy::SkimMatcher::default()),
+            MatcherKind::Clangd => Box::new(fuzzy::ClangdMatcher::default()),
+            MatcherKind::Exact => Box::new(exact::ExactMatcher),
+            MatcherKind::Prefix => Box::new(segment::SegmentPrefixMatcher),
+            MatcherKind::Regex => Box::new(pattern::RegexMatcher::default()),
+        }
+    }
+}
+
+/// Whether matching `input` should ignore case: only if it doesn't contain uppercase characters.
+fn is_smart_case_insensitive(input: &str) -> bool {
+    !input.chars().any(char::is_uppercase)
+}
+
+/// Compares two characters, ignoring case if `ignore_case` is set.
+fn chars_eq(lhs: char, rhs: char, ignore_case: bool) -> bool {
+    lhs == rhs || (ignore_case && lhs.to_lowercase().eq(rhs.to_lowercase()))
+}
+
+/// Converts byte `range` of `s` into the list of indices of the characters it spans.
+fn char_indices_of(s: &str, range: std::ops::Range<usize>) -> impl Iterator<Item = usize> + '_ {
+    s.char_indices()
+        .enumerate()
+        .filter(move |(_, (byte_index, _))| range.contains(byte_index))
+        .map(|(char_index, _)| char_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indices(kind: MatcherKind, repr: &str, input: &str) -> Option<Vec<usize>> {
+        kind.matcher()
+            .match_indices(repr, input)
+            .map(|(_, indices)| indices)
+    }
+
+    #[test]
+    fn parse_matcher_kind() {
+        for kind in MatcherKind::ALL {
+            assert_eq!(MatcherKind::parse(kind.name()), Some(kind));
+        }
+        assert_eq!(MatcherKind::parse("fzf"), None);
+    }
+
+    #[test]
+    fn next_matcher_kind_wraps_around() {
+        assert_eq!(MatcherKind::Skim.next(), MatcherKind::Clangd);
+        assert_eq!(MatcherKind::Regex.next(), MatcherKind::Skim);
+    }
+
+    #[test]
+    fn exact_matcher() {
+        assert_eq!(
+            indices(MatcherKind::Exact, "nix-config", "conf"),
+            Some(vec![4, 5, 6, 7])
+        );
+        assert_eq!(
+            indices(MatcherKind::Exact, "nix-Config", "conf"),
+            Some(vec![4, 5, 6, 7])
+        );
+        assert_eq!(indices(MatcherKind::Exact, "nix-config", "Conf"), None);
+        assert_eq!(indices(MatcherKind::Exact, "nix-config", "ncf"), None);
+    }
+
+    #[test]
+    fn segment_prefix_matcher() {
+        assert_eq!(
+            indices(MatcherKind::Prefix, "src/nix/config-nvim", "ni/co"),
+            Some(vec![4, 5, 8, 9])
+        );
+        assert_eq!(
+            indices(MatcherKind::Prefix, "src/nix/config-nvim", "conf"),
+            Some(vec![8, 9, 10, 11])
+        );
+        assert_eq!(indices(MatcherKind::Prefix, "src/nix/config", "onf"), None);
+        assert_eq!(
+            indices(MatcherKind::Prefix, "src/nix/config", "co/ni"),
+            None
+        );
+    }
+
+    #[test]
+    fn regex_matcher() {
+        assert_eq!(
+            indices(MatcherKind::Regex, "src/nix-config", "^src/n.x"),
+            Some(vec![0, 1, 2, 3, 4, 5, 6])
+        );
+        assert_eq!(indices(MatcherKind::Regex, "src/nix-config", "^nix"), None);
+        // Invalid expressions, e.g. while the user is still typing, match nothing.
+        assert_eq!(indices(MatcherKind::Regex, "src/nix-config", "nix("), None);
+    }
+
+    #[test]
+    fn empty_input_matches_everything() {
+        for kind in MatcherKind::ALL {
+            assert_eq!(indices(kind, "nix-config", ""), Some(vec![]), "{kind:?}");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// Regular expression matcher.
+use super::{char_indices_of, is_smart_case_insensitive, Matcher};
+
+use regex::{Regex, RegexBuilder};
+use std::cell::RefCell;
+
+/// Matches choices against the user input interpreted as a regular expression. Matching ignores
+/// case unless the input contains uppercase characters.
+///
+/// Invalid expressions, which are common while the user is still typing, don't match anything.
+#[derive(Default)]
+pub(super) struct RegexMatcher {
+    /// The last compiled expression, keyed by the user input it was compiled from. Avoids compiling
+    /// the same expression once per choice.
+    cache: RefCell<Option<(String, Option<Regex>)>>,
+}
+
+impl RegexMatcher {
+    fn compile(input: &str) -> Option<Regex> {
+        RegexBuilder::new(input)
+            .case_insensitive(is_smart_case_insensitive(input))
+            .build()
+            .ok()
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn match_indices(&self, repr: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        let mut cache = self.cache.borrow_mut();
+        if !matches!(cache.as_ref(), Some((cached, _)) if cached == input) {
+            *cache = Some((input.to_string(), Self::compile(input)));
+        }
+        let (_, regex) = cache.as_ref()?;
+
+        let found = regex.as_ref()?.find(repr)?;
+        // Favor earlier matches, then shorter choices.
+        let score = -(found.start() as i64) - repr.len() as i64;
+
+        Some((score, char_indices_of(repr, found.range()).collect()))
+    }
+}
@@ -0,0 +1,268 @@
+/// fzf-style extended search syntax.
+///
+/// The user input is split into space-separated terms, all of which must match. Each term is
+/// matched according to its prefix and suffix:
+///
+/// | Term      | Matches choices…                           |
+/// |-----------|--------------------------------------------|
+/// | `infra`   | fuzzy matching `infra`                     |
+/// | `'infra`  | containing `infra`                         |
+/// | `^infra`  | starting with `infra`                      |
+/// | `-api$`   | ending with `-api`                         |
+/// | `^infra$` | equal to `infra`                           |
+/// | `!infra`  | not containing `infra`                     |
+/// | `!^infra` | not starting with `infra` (same for `$`)   |
+/// | `a \| b`  | matching either `a` or `b`                 |
+///
+/// Spaces can be escaped with a backslash (`\ `) to be matched literally.
+use super::{chars_eq, exact::ExactMatcher, is_smart_case_insensitive, Matcher};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AtomKind {
+    /// Matched with the matcher in use.
+    Fuzzy,
+    /// `'`-prefixed: substring match.
+    Exact,
+    /// `^`-prefixed: prefix match.
+    Prefix,
+    /// `$`-suffixed: suffix match.
+    Suffix,
+    /// `^`-prefixed and `$`-suffixed: the whole choice must match.
+    Equal,
+}
+
+/// A single search pattern.
+#[derive(Debug, PartialEq)]
+struct Atom {
+    kind: AtomKind,
+    text: String,
+    /// `!`-prefixed: the atom matches choices that don't match the pattern.
+    negated: bool,
+}
+
+/// A list of `|`-separated alternatives, at least one of which must match.
+type Term = Vec<Atom>;
+
+/// A parsed user input. An empty query matches all choices.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parses `input` using the extended search syntax.
+    pub(crate) fn parse(input: &str) -> Self {
+        let mut terms: Vec<Term> = Vec::new();
+        let mut is_alternative = false;
+        for token in tokenize(input) {
+            if token == "|" {
+                is_alternative = !terms.is_empty();
+                continue;
+            }
+            let Some(atom) = Atom::parse(&token) else {
+                continue;
+            };
+            match terms.last_mut() {
+                Some(term) if is_alternative => term.push(atom),
+                _ => terms.push(vec![atom]),
+            }
+            is_alternative = false;
+        }
+
+        Self { terms }
+    }
+
+    /// Interprets `input` as a single pattern, e.g. for matchers with their own syntax.
+    pub(crate) fn verbatim(input: &str) -> Self {
+        let terms = match input {
+            "" => vec![],
+            _ => vec![vec![Atom {
+                kind: AtomKind::Fuzzy,
+                text: input.to_string(),
+                negated: false,
+            }]],
+        };
+
+        Self { terms }
+    }
+
+    /// Matches `repr` against all terms of the query, using `matcher` for fuzzy patterns. Returns
+    /// the sum of the terms' scores and the union of their matched character indices.
+    pub(crate) fn match_indices(
+        &self,
+        matcher: &dyn Matcher,
+        repr: &str,
+    ) -> Option<(i64, Vec<usize>)> {
+        let mut score = 0;
+        let mut indices = Vec::new();
+        for term in &self.terms {
+            let (term_score, term_indices) = term
+                .iter()
+                .filter_map(|atom| atom.match_indices(matcher, repr))
+                .max_by_key(|(score, _)| *score)?;
+            score += term_score;
+            indices.extend(term_indices);
+        }
+        indices.sort_unstable();
+        indices.dedup();
+
+        Some((score, indices))
+    }
+}
+
+impl Atom {
+    /// Parses a single token. Returns `None` if the token doesn't contain any pattern to match,
+    /// e.g. a lone `!` while the user is still typing.
+    fn parse(token: &str) -> Option<Self> {
+        let (negated, token) = match token.strip_prefix('!') {
+            Some(token) => (true, token),
+            None => (false, token),
+        };
+        let (kind, text) = if let Some(text) = token.strip_prefix('\'') {
+            (AtomKind::Exact, text)
+        } else if let Some(text) = token.strip_prefix('^') {
+            match text.strip_suffix('$') {
+                Some(text) => (AtomKind::Equal, text),
+                None => (AtomKind::Prefix, text),
+            }
+        } else if let Some(text) = token.strip_suffix('$') {
+            (AtomKind::Suffix, text)
+        } else if negated {
+            // Like fzf, negated patterns are matched exactly: fuzzy negations rule out too much.
+            (AtomKind::Exact, token)
+        } else {
+            (AtomKind::Fuzzy, token)
+        };
+
+        (!text.is_empty()).then(|| Self {
+            kind,
+            text: text.to_string(),
+            negated,
+        })
+    }
+
+    fn match_indices(&self, matcher: &dyn Matcher, repr: &str) -> Option<(i64, Vec<usize>)> {
+        let found = match self.kind {
+            AtomKind::Fuzzy => matcher.match_indices(repr, &self.text),
+            AtomKind::Exact => ExactMatcher.match_indices(repr, &self.text),
+            AtomKind::Prefix | AtomKind::Suffix | AtomKind::Equal => self.match_anchored(repr),
+        };
+
+        match (found, self.negated) {
+            (Some(found), false) => Some(found),
+            (None, true) => Some((0, Vec::new())),
+            _ => None,
+        }
+    }
+
+    fn match_anchored(&self, repr: &str) -> Option<(i64, Vec<usize>)> {
+        let ignore_case = is_smart_case_insensitive(&self.text);
+        let repr = repr.chars().collect::<Vec<_>>();
+        let text = self.text.chars().collect::<Vec<_>>();
+        let start = match self.kind {
+            AtomKind::Prefix => 0,
+            AtomKind::Suffix => repr.len().checked_sub(text.len())?,
+            AtomKind::Equal if repr.len() == text.len() => 0,
+            _ => return None,
+        };
+
+        let is_match = repr.len() >= start + text.len()
+            && text
+                .iter()
+                .zip(&repr[start..])
+                .all(|(&lhs, &rhs)| chars_eq(lhs, rhs, ignore_case));
+
+        is_match.then(|| (0, (start..start + text.len()).collect()))
+    }
+}
+
+/// Splits `input` on unescaped spaces.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&' ') => token.extend(chars.next()),
+            ' ' => tokens.extend((!token.is_empty()).then(|| std::mem::take(&mut token))),
+            _ => token.push(ch),
+        }
+    }
+    tokens.extend((!token.is_empty()).then_some(token));
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::MatcherKind;
+
+    fn atom(kind: AtomKind, text: &str, negated: bool) -> Atom {
+        Atom {
+            kind,
+            text: text.to_string(),
+            negated,
+        }
+    }
+
+    fn indices(query: &str, repr: &str) -> Option<Vec<usize>> {
+        Query::parse(query)
+            .match_indices(MatcherKind::Skim.matcher().as_ref(), repr)
+            .map(|(_, indices)| indices)
+    }
+
+    #[test]
+    fn parse_extended_query() {
+        assert_eq!(
+            Query::parse("infra !^archive/ -api$ | -svc$ ^src$ 'my\\ app"),
+            Query {
+                terms: vec![
+                    vec![atom(AtomKind::Fuzzy, "infra", false)],
+                    vec![atom(AtomKind::Prefix, "archive/", true)],
+                    vec![
+                        atom(AtomKind::Suffix, "-api", false),
+                        atom(AtomKind::Suffix, "-svc", false)
+                    ],
+                    vec![atom(AtomKind::Equal, "src", false)],
+                    vec![atom(AtomKind::Exact, "my app", false)],
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn parse_incomplete_query() {
+        assert_eq!(Query::parse("  ! ' ^ | "), Query::default());
+        assert_eq!(
+            Query::parse("| nix |"),
+            Query {
+                terms: vec![vec![atom(AtomKind::Fuzzy, "nix", false)]]
+            }
+        );
+    }
+
+    #[test]
+    fn terms_are_anded() {
+        assert!(indices("infra -api$", "src/infra-api").is_some());
+        assert!(indices("infra -api$", "src/infra-web").is_none());
+        assert!(indices("infra !^archive/", "archive/infra-api").is_none());
+        assert!(indices("infra !^archive/", "src/infra-api").is_some());
+    }
+
+    #[test]
+    fn alternatives_are_ored() {
+        assert!(indices("-api$ | -web$", "src/infra-api").is_some());
+        assert!(indices("-api$ | -web$", "src/infra-web").is_some());
+        assert!(indices("-api$ | -web$", "src/infra-svc").is_none());
+    }
+
+    #[test]
+    fn indices_are_merged() {
+        assert_eq!(
+            indices("^src 'nix -api$", "src/nix-api"),
+            Some(vec![0, 1, 2, 4, 5, 6, 7, 8, 9, 10])
+        );
+        assert_eq!(indices("!web ^src", "src/nix-api"), Some(vec![0, 1, 2]));
+    }
+}
@@ -0,0 +1,54 @@
+/// Path segment prefix matcher.
+use super::{chars_eq, is_smart_case_insensitive, Matcher};
+
+/// Subtracted from the score for each path segment following the last matched segment.
+const TRAILING_SEGMENT_PENALTY: i64 = 16;
+
+/// Matches choices whose path segments start with the `/`-separated parts of the user input, in
+/// order. For example, `ni/co` matches `src/nix/config-nvim`.
+///
+/// Parts are matched against the last possible segments so that matches on the directory name
+/// rank higher. Matching ignores case unless the input contains uppercase characters.
+pub(super) struct SegmentPrefixMatcher;
+
+impl Matcher for SegmentPrefixMatcher {
+    fn match_indices(&self, repr: &str, input: &str) -> Option<(i64, Vec<usize>)> {
+        let ignore_case = is_smart_case_insensitive(input);
+        let repr = repr.chars().collect::<Vec<_>>();
+
+        // The character index at which each segment starts, along with its characters.
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for segment in repr.split(|&ch| ch == '/') {
+            segments.push((start, segment));
+            start += segment.len() + 1;
+        }
+
+        let mut indices = Vec::new();
+        let mut remaining_segments = segments.len();
+        let mut last_matched_segment = None;
+        for part in input.split('/').filter(|part| !part.is_empty()).rev() {
+            let part = part.chars().collect::<Vec<_>>();
+            let index = segments[..remaining_segments]
+                .iter()
+                .rposition(|(_, segment)| {
+                    segment.len() >= part.len()
+                        && part
+                            .iter()
+                            .zip(segment.iter())
+                            .all(|(&lhs, &rhs)| chars_eq(lhs, rhs, ignore_case))
+                })?;
+            let (start, _) = segments[index];
+            indices.extend((start..start + part.len()).rev());
+            remaining_segments = index;
+            last_matched_segment.get_or_insert(index);
+        }
+        indices.reverse();
+
+        let trailing_segments =
+            last_matched_segment.map_or(0, |index| segments.len() - index - 1) as i64;
+        let score = -trailing_segments * TRAILING_SEGMENT_PENALTY - repr.len() as i64;
+
+        Some((score, indices))
+    }
+}
@@ -1,20 +1,73 @@
+use crate::bootstrap::SessionOccupancy;
 use crate::core::{PluginError, PluginUpdateLoop, Result, ResultIterator};
-use crate::fuzzy_search_context::{FuzzySearchContext, PathEntry};
+use crate::frecency::FrecencyStore;
+use crate::fuzzy_search_context::{
+    FuzzySearchContext, PathEntry, Picker, ScanSource, SessionEntry, SessionPrompt, SessionStatus,
+};
 use crate::hash;
-use crate::protocol::{PathFinderPluginCommand, PathFinderPluginConfig};
+use crate::keymap::{Action, BatchTarget, InputMode, KeyResolution, OpenTarget};
+use crate::line_editor::LineEditor;
+use crate::protocol::{
+    announce_instance_message, instance_running_message, FrecencyCommand, PathFinderPluginCommand,
+    PathFinderPluginConfig, ScanRoot,
+};
+use crate::session_alias::SessionAliasStore;
 use crate::ui::{Renderer, PANE_TITLE};
 #[cfg(not(feature = "zellij_fallback_fs_api"))]
 use crate::workers::protocol::{
-    FileSystemWorkerMessage, RepositoryCrawlerRequest, RepositoryCrawlerResponse,
+    FileSystemWorkerMessage, RepositoryCrawlerCompletion, RepositoryCrawlerFailure,
+    RepositoryCrawlerRequest, RepositoryCrawlerResponse,
 };
 
 use anyhow::Context as _;
+use data_encoding::BASE64;
+use project_markers::MarkerKind;
+#[cfg(feature = "zellij_run_command_api")]
+use project_markers::{
+    DESCEND_ENV_VARIABLE, EXCLUDE_ENV_VARIABLE, FOLLOW_SYMLINKS_ENV_VARIABLE,
+    IGNORE_FILES_ENV_VARIABLE, PROJECT_MARKERS_ENV_VARIABLE,
+};
 use std::{
     collections::{BTreeMap, BTreeSet},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use zellij_tile::prelude::*;
 
+/// The interval, in seconds, at which the activity indicator is animated while scans are pending.
+const SPINNER_INTERVAL: f64 = 0.1;
+
+/// The key under which the external program is recorded in the context of `run_command` requests,
+/// to tell their results apart.
+#[cfg(feature = "zellij_run_command_api")]
+const SCAN_SOURCE_CONTEXT_KEY: &'static str = "scan_source";
+
+/// The key under which the root scanned by `host_scan_program` is recorded in the context of
+/// `run_command` requests, along with the program under [SCAN_SOURCE_CONTEXT_KEY].
+#[cfg(feature = "zellij_run_command_api")]
+const HOST_SCAN_ROOT_CONTEXT_KEY: &str = "host_scan_root";
+
+/// The key under which the session name is recorded in the context of `run_command` requests
+/// creating background sessions.
+#[cfg(feature = "zellij_run_command_api")]
+const BACKGROUND_SESSION_CONTEXT_KEY: &'static str = "background_session";
+
+/// The key under which the former name of a session is recorded in the context of `run_command`
+/// requests renaming it to the current naming scheme. The new name and the directory of the session
+/// are recorded under [SESSION_NAME_CONTEXT_KEY] and [RELATIVE_CWD_CONTEXT_KEY].
+#[cfg(feature = "zellij_run_command_api")]
+const MIGRATED_SESSION_CONTEXT_KEY: &'static str = "migrated_session";
+
+/// The key under which the former name of a session is recorded in the context of `run_command`
+/// requests renaming it at the user's request.
+#[cfg(feature = "zellij_run_command_api")]
+const RENAMED_SESSION_CONTEXT_KEY: &str = "renamed_session";
+
+#[cfg(feature = "zellij_run_command_api")]
+const SESSION_NAME_CONTEXT_KEY: &'static str = "session_name";
+
+#[cfg(feature = "zellij_run_command_api")]
+const RELATIVE_CWD_CONTEXT_KEY: &'static str = "relative_cwd";
+
 /// The plugin state, to be registered against Zellij's API.
 ///
 /// It contains the plugin's user configuration, as well as cached state used for operating
@@ -56,6 +109,9 @@ pub(crate) struct PrimeHopperPlugin {
     // session (because Zellij does not appreciate switching to the current session).
     /// The name of the session the plugin is running in.
     current_session_name: Option<String>,
+    /// What lives in the current session besides the plugin, to decide whether a bootstrap
+    /// session can be killed after switching away from it.
+    current_session_occupancy: SessionOccupancy,
     /// The name of all sessions managed by the Zellij daemon serving the session the plugin is
     /// running in.
     all_sessions_name: BTreeSet<String>,
@@ -65,6 +121,19 @@ pub(crate) struct PrimeHopperPlugin {
     /// Events queued until the first `Event::SessionUpdate` is received.
     event_queue: Vec<Event>,
 
+    /// Whether a timer is already running to animate the activity indicator.
+    spinner_armed: bool,
+
+    /// The keys pressed so far that are the beginning of a bound key sequence, e.g. `g` for `g g`.
+    pending_keys: Vec<KeyWithModifier>,
+
+    /// The session name of each directory switched to, to find sessions created under older naming
+    /// schemes.
+    session_aliases: SessionAliasStore,
+
+    /// Text to copy to the clipboard on the next render.
+    clipboard: Option<String>,
+
     /// The plugin context, that keeps track of some volatile state.
     context: FuzzySearchContext,
     /// Handles drawing the list of results on the screen, as well as dealing with user selection.
@@ -83,6 +152,8 @@ impl ZellijPlugin for PrimeHopperPlugin {
         request_permission(&[
             PermissionType::ChangeApplicationState,
             PermissionType::ReadApplicationState,
+            // Required to detect already running instances of the plugin.
+            PermissionType::MessageAndLaunchOtherPlugins,
             #[cfg(feature = "zellij_run_command_api")]
             PermissionType::RunCommands,
         ]);
@@ -95,9 +166,40 @@ impl ZellijPlugin for PrimeHopperPlugin {
             #[cfg(feature = "zellij_run_command_api")]
             EventType::RunCommandResult,
             EventType::SessionUpdate,
+            EventType::Timer,
         ]);
 
-        self.config.load(&configuration);
+        for error in self.config.load(&configuration) {
+            self.context.log_error(error);
+        }
+        self.context
+            .set_current_session_display(self.config.current_session_display);
+        self.context
+            .set_boost_active_sessions(self.config.boost_active_sessions);
+        self.context
+            .set_session_name_template(self.config.session_name_template.clone());
+        self.context.set_matcher_kind(self.config.matcher);
+        self.renderer.set_keymap(&self.config.keymap);
+        if self.config.keymap.is_modal() {
+            self.context.enable_input_modes();
+        }
+        match FrecencyStore::load(&self.config.frecency_store) {
+            Ok(frecency) => {
+                self.context
+                    .set_frecency(frecency, get_plugin_ids().initial_cwd);
+            }
+            Err(error) => {
+                eprintln!("failed to load frecency store: {error:?}");
+                self.context.log_error(PluginError::UnexpectedError(error));
+            }
+        }
+        match SessionAliasStore::load(&self.config.session_alias_store) {
+            Ok(session_aliases) => self.session_aliases = session_aliases,
+            Err(error) => {
+                eprintln!("failed to load session alias store: {error:?}");
+                self.context.log_error(PluginError::UnexpectedError(error));
+            }
+        }
 
         if self.permissions_granted {
             // Initialize the plugin immediatelly since permissions have already been granted.
@@ -124,6 +226,11 @@ impl ZellijPlugin for PrimeHopperPlugin {
     }
 
     fn render(&mut self, rows: usize, cols: usize) {
+        // Zellij does not expose a clipboard API to plugins: use an OSC 52 escape sequence instead,
+        // which Zellij forwards to the terminal emulator.
+        if let Some(text) = self.clipboard.take() {
+            print!("\u{1b}]52;c;{}\u{7}", BASE64.encode(text.as_bytes()));
+        }
         let frame = self.renderer.next_frame(rows, cols, &self.context);
         print!("{frame}");
     }
@@ -146,22 +253,29 @@ impl PrimeHopperPlugin {
         // Give the plugin pane a more concise name.
         rename_plugin_pane(get_plugin_ids().plugin_id, PANE_TITLE);
 
+        // Let an already running instance take over, if any.
+        if self.config.single_instance {
+            pipe_message_to_plugin(announce_instance_message(&get_plugin_ids().initial_cwd));
+        }
+
         match self.config.pipe_message.take() {
             Some(pipe_message) => self.handle_pipe_message(pipe_message),
             None => PluginUpdateLoop::NoUpdates,
         }
     }
 
-    // TODO: consider adding activity feedback (e.g. spinner) to the UI while waiting for the
-    // results.
     fn handle_pipe_message(&mut self, message: PipeMessage) -> PluginUpdateLoop {
         use PathFinderPluginCommand::*;
         let result = match message.into() {
-            // Start scanning the /host. The scan always happens asynchronously, and responses are
-            // posted back to the plugin through the `::update(…)` callback.
+            // Start scanning the requested roots under /host. The scan always happens
+            // asynchronously, and responses are posted back to the plugin through the `::update(…)`
+            // callback.
             // The scanning method (either through a background plugin worker or via the Zellij API) is
             // dictated by the `zellij_fallback_fs_api` feature flag.
-            ScanRepositoryRoot { max_depth } => self.start_async_root_scan(max_depth),
+            ScanRepositoryRoot { roots } => {
+                self.start_async_root_scan(roots);
+                Ok(())
+            }
 
             // Run one or more external commands to get the list of path. While the command
             // execution is asynchronous from the plugin point of view, the results are sent back
@@ -176,9 +290,33 @@ impl PrimeHopperPlugin {
                 })
                 .collect(),
 
+            AnnounceInstance { plugin_id, cwd } => {
+                self.on_instance_announced(plugin_id, cwd);
+                Ok(())
+            }
+
+            Frecency(command) => {
+                match command {
+                    FrecencyCommand::Reset => self.context.reset_frecency(),
+                    FrecencyCommand::Prune { max_age_days } => {
+                        self.context.prune_frecency(max_age_days)
+                    }
+                };
+                self.save_frecency()
+            }
+
+            // A running instance superseded this one.
+            InstanceRunning => {
+                self.terminate();
+                Ok(())
+            }
+
             PluginCommandError(error) => Err(error.into()),
         };
 
+        // Animate the activity indicator until all sources have reported.
+        self.arm_spinner();
+
         match result {
             Ok(_) => PluginUpdateLoop::MarkDirty,
             Err(error) => {
@@ -189,9 +327,141 @@ impl PrimeHopperPlugin {
         }
     }
 
-    fn start_async_root_scan(&self, max_depth: usize) -> anyhow::Result<()> {
-        // TODO: pass these arguments through plugin configuration.
-        self.post_repository_crawler_task(PathBuf::from("/host"), max_depth)
+    fn arm_spinner(&mut self) {
+        if !self.spinner_armed && self.context.is_scanning() {
+            self.spinner_armed = true;
+            set_timeout(SPINNER_INTERVAL);
+        }
+    }
+
+    fn handle_timer(&mut self) -> PluginUpdateLoop {
+        self.spinner_armed = false;
+        let update = self.context.tick_spinner();
+        self.arm_spinner();
+        update
+    }
+
+    fn save_frecency(&self) -> anyhow::Result<()> {
+        self.context.frecency().save(&self.config.frecency_store)
+    }
+
+    /// Handles a new instance of the plugin announcing itself.
+    ///
+    /// If both instances share the same CWD, this instance is brought back into focus and the new
+    /// one is closed. Otherwise, the new instance has access to a different part of the filesystem
+    /// and takes over.
+    fn on_instance_announced(&mut self, plugin_id: u32, cwd: PathBuf) {
+        let plugin_ids = get_plugin_ids();
+        if !self.config.single_instance || plugin_id == plugin_ids.plugin_id {
+            return;
+        }
+
+        if cwd == plugin_ids.initial_cwd {
+            show_self(true);
+            pipe_message_to_plugin(instance_running_message(plugin_id));
+        } else {
+            self.terminate();
+        }
+    }
+
+    /// Fans out one crawler task per root. Falls back to the configured roots if `roots` is empty.
+    /// Roots that cannot be scanned are reported without preventing the others from being scanned.
+    fn start_async_root_scan(&mut self, roots: Vec<ScanRoot>) {
+        let roots = if roots.is_empty() {
+            self.config.scan_roots.clone()
+        } else {
+            roots
+        };
+
+        for root in roots {
+            eprintln!("Scanning root: {root:?}");
+            if let Err(error) = self.start_root_scan(root) {
+                eprintln!("failed to scan root: {error:?}");
+                self.context
+                    .log_error(PluginError::FileSystemScanFailed(error));
+            }
+        }
+    }
+
+    fn start_root_scan(&mut self, root: ScanRoot) -> anyhow::Result<()> {
+        let Some(path) = self.relative_scan_root(&root.path) else {
+            return self.start_host_root_scan(root);
+        };
+
+        // Zellij's API does not report when a scan is complete.
+        #[cfg(not(feature = "zellij_fallback_fs_api"))]
+        {
+            let source = ScanSource::Crawler(path.clone());
+            self.context.start_scan(source.clone());
+            self.post_repository_crawler_task(path, root.max_depth)
+                .inspect_err(|_| {
+                    self.context.fail_scan(source);
+                })
+        }
+        #[cfg(feature = "zellij_fallback_fs_api")]
+        self.post_repository_crawler_task(path, root.max_depth)
+    }
+
+    /// Returns `root` relative to the plugin's CWD, which is mounted under `/host`, or `None` if
+    /// the plugin cannot access it. A leading `~` is expanded if the plugin knows the home
+    /// directory, which Zellij usually does not tell plugins.
+    fn relative_scan_root(&self, root: &Path) -> Option<PathBuf> {
+        let root = match root.strip_prefix("~") {
+            Ok(relative) => PathBuf::from(std::env::var_os("HOME")?).join(relative),
+            Err(_) => root.to_path_buf(),
+        };
+        if root.is_relative() {
+            return Some(root);
+        }
+        let relative = root.strip_prefix(get_plugin_ids().initial_cwd).ok()?;
+        Some(relative.to_path_buf())
+    }
+
+    /// Scans `root`, which the plugin cannot access, with the `host_scan_program` on the host.
+    #[cfg(feature = "zellij_run_command_api")]
+    fn start_host_root_scan(&mut self, root: ScanRoot) -> anyhow::Result<()> {
+        let cwd = get_plugin_ids().initial_cwd;
+        let Some(program) = self.config.host_scan_program.as_ref() else {
+            return Err(PluginError::ConfigurationError {
+                reason: format!(
+                    "scan root {:?} is outside of the plugin's CWD {cwd:?}, set `host_scan_program` to scan it",
+                    root.path
+                ),
+            }
+            .into());
+        };
+        let program = program
+            .to_str()
+            .with_context(|| format!("failed to decode path (invalid UTF-8?): {program:?}"))?;
+        let path = root
+            .path
+            .to_str()
+            .with_context(|| format!("failed to decode path (invalid UTF-8?): {:?}", root.path))?;
+
+        run_command_with_env_variables_and_cwd(
+            &[program, path, "--max-depth", &root.max_depth.to_string()],
+            self.scan_env_variables(),
+            cwd,
+            BTreeMap::from([
+                (SCAN_SOURCE_CONTEXT_KEY.to_string(), program.to_string()),
+                (HOST_SCAN_ROOT_CONTEXT_KEY.to_string(), path.to_string()),
+            ]),
+        );
+        self.context.start_scan(ScanSource::HostCrawler(root.path));
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "zellij_run_command_api"))]
+    fn start_host_root_scan(&mut self, root: ScanRoot) -> anyhow::Result<()> {
+        Err(PluginError::ConfigurationError {
+            reason: format!(
+                "scan root {:?} is outside of the plugin's CWD {:?}, scanning it requires the `run_command` API",
+                root.path,
+                get_plugin_ids().initial_cwd
+            ),
+        }
+        .into())
     }
 
     #[cfg(not(feature = "zellij_fallback_fs_api"))]
@@ -199,8 +469,9 @@ impl PrimeHopperPlugin {
         use crate::marshall_plugin::serialize;
 
         // Scan the host folder using the FS worker (preferred).
-        // This API posts its results back to the plugin using the `Event::CustomMessage` event
-        // with a `FileSystemWorkerMessage::Crawl` message.
+        // This API streams its results back to the plugin using the `Event::CustomMessage` event
+        // with `FileSystemWorkerMessage::CrawlBatch` messages, followed by a final
+        // `FileSystemWorkerMessage::CrawlComplete` message.
         // NOTE: The `PluginMessage::new_to_worker(…)`'s `worker_name` argument must match the
         // worker's namespace specified when registering the worker: to send messages to the worker
         // declared with `test_worker` namespace, pass `"test"` to `::new_to_worker(…)`.
@@ -208,8 +479,16 @@ impl PrimeHopperPlugin {
             "file_system", // Post to the `file_system_worker` namespace.
             &serialize(&FileSystemWorkerMessage::Crawl)
                 .with_context(|| "serializing outbound message to `file_system` worker")?,
-            &serialize(&RepositoryCrawlerRequest { root, max_depth })
-                .with_context(|| "serializing outbound request to `file_system` worker")?,
+            &serialize(&RepositoryCrawlerRequest {
+                root,
+                max_depth,
+                markers: self.config.markers.clone(),
+                excludes: self.config.excludes.clone(),
+                descend: self.config.descend_into_repositories,
+                follow_symlinks: self.config.follow_symlinks,
+                host_cwd: get_plugin_ids().initial_cwd,
+            })
+            .with_context(|| "serializing outbound request to `file_system` worker")?,
         ));
 
         Ok(())
@@ -223,13 +502,13 @@ impl PrimeHopperPlugin {
         // NOTE: This API is a stop-gap method that allows plugins to scan a folder on the /host
         // filesystem and get back a list of files. This is a workaround for the Zellij WASI
         // runtime being extremely slow. This API might be removed in the future.
-        scan_host_folder(&root);
+        scan_host_folder(&PathBuf::from("/host").join(root));
 
         Ok(())
     }
 
     #[cfg(feature = "zellij_run_command_api")]
-    fn run_external_pathfinder_command(&self, program: PathBuf) -> anyhow::Result<()> {
+    fn run_external_pathfinder_command(&mut self, program: PathBuf) -> anyhow::Result<()> {
         let Some(command) = program.to_str() else {
             return Err(PluginError::InvalidPipeMessagePayloadError(format!(
                 "failed to decode `{program:?}`"
@@ -247,11 +526,46 @@ impl PrimeHopperPlugin {
             .into());
         };
 
-        run_command(&[&command, root], BTreeMap::new());
+        run_command_with_env_variables_and_cwd(
+            &[&command, root],
+            self.scan_env_variables(),
+            cwd.clone(),
+            BTreeMap::from([(SCAN_SOURCE_CONTEXT_KEY.to_string(), command.to_string())]),
+        );
+        self.context
+            .start_scan(ScanSource::ExternalProgram(program.clone()));
 
         Ok(())
     }
 
+    /// The environment of external programs. Programs that crawl directories themselves, e.g.
+    /// `find-git-repositories`, are told about the configured markers and excludes through it.
+    #[cfg(feature = "zellij_run_command_api")]
+    fn scan_env_variables(&self) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (
+                PROJECT_MARKERS_ENV_VARIABLE.to_string(),
+                self.config.markers.project_markers_spec(),
+            ),
+            (
+                EXCLUDE_ENV_VARIABLE.to_string(),
+                self.config.excludes.patterns_spec(),
+            ),
+            (
+                IGNORE_FILES_ENV_VARIABLE.to_string(),
+                self.config.excludes.ignore_files().to_string(),
+            ),
+            (
+                DESCEND_ENV_VARIABLE.to_string(),
+                self.config.descend_into_repositories.to_string(),
+            ),
+            (
+                FOLLOW_SYMLINKS_ENV_VARIABLE.to_string(),
+                self.config.follow_symlinks.to_string(),
+            ),
+        ])
+    }
+
     /// Consumes as many of the queued events as possible, and returns either the final combined
     /// [RenderStrategy] value, or the first error that occurred.
     ///
@@ -267,20 +581,66 @@ impl PrimeHopperPlugin {
     #[cfg(not(feature = "zellij_fallback_fs_api"))]
     fn handle_custom_message(&mut self, message: String, payload: String) -> Result {
         use crate::marshall_plugin::deserialize;
-        assert!(
-            matches!(
-                deserialize(&message)
-                    .with_context(|| "deserializing message from `file_system` worker")?,
-                FileSystemWorkerMessage::Crawl
-            ),
-            "unsupported message received from own background worker"
-        );
-        let RepositoryCrawlerResponse { repositories } = deserialize(&payload)
-            .with_context(|| "deserializing response from `file_system` worker")?;
+        use std::path::Component;
 
-        Ok(self
-            .context
-            .add_choices(repositories.into_iter().map(Into::<PathEntry>::into)))
+        match deserialize(&message)
+            .with_context(|| "deserializing message from `file_system` worker")?
+        {
+            FileSystemWorkerMessage::CrawlBatch => {
+                let RepositoryCrawlerResponse {
+                    root,
+                    repositories,
+                    labelled,
+                } = deserialize(&payload)
+                    .with_context(|| "deserializing response from `file_system` worker")?;
+
+                // Prefix results with their root so that the user can tell them apart. Results
+                // from the CWD are left as-is to keep labels concise (and session names stable).
+                let is_cwd = root.components().all(|c| c == Component::CurDir);
+                let prefix = |path: PathBuf| if is_cwd { path } else { root.join(path) };
+                // Labelled projects living under the CWD, e.g. worktrees, are given relative paths
+                // for the same reason.
+                let cwd = get_plugin_ids().initial_cwd;
+                let labelled = labelled.into_iter().map(|(label, (path, marker))| {
+                    let path = match path.strip_prefix(&cwd) {
+                        Ok(relative) => relative.to_path_buf(),
+                        Err(_) => path,
+                    };
+                    PathEntry::new(prefix(label), path).with_marker(marker)
+                });
+                Ok(self.context.add_choices(
+                    repositories
+                        .into_iter()
+                        .map(|(repository, marker)| {
+                            PathEntry::from(prefix(repository)).with_marker(marker)
+                        })
+                        .chain(labelled),
+                ))
+            }
+            FileSystemWorkerMessage::CrawlComplete => {
+                let RepositoryCrawlerCompletion {
+                    root,
+                    repository_count,
+                } = deserialize(&payload)
+                    .with_context(|| "deserializing completion from `file_system` worker")?;
+                eprintln!("Scan of {root:?} complete: {repository_count} repositories found");
+
+                Ok(self.context.complete_scan(ScanSource::Crawler(root)))
+            }
+            FileSystemWorkerMessage::CrawlFailed => {
+                let RepositoryCrawlerFailure { root, reason } = deserialize(&payload)
+                    .with_context(|| "deserializing failure from `file_system` worker")?;
+                self.context.fail_scan(ScanSource::Crawler(root.clone()));
+                self.context
+                    .log_error(PluginError::FileSystemScanFailed(anyhow!(
+                        "scan of {root:?} failed: {reason}"
+                    )))
+                    .into()
+            }
+            FileSystemWorkerMessage::Crawl => {
+                unreachable!("unsupported message received from own background worker")
+            }
+        }
     }
 
     #[cfg(feature = "zellij_fallback_fs_api")]
@@ -325,12 +685,29 @@ impl PrimeHopperPlugin {
         exitcode: Option<i32>,
         stdout: Vec<u8>,
         stderr: Vec<u8>,
+        context: BTreeMap<String, String>,
     ) -> Result {
         use core::str;
 
         use crate::marshall_command;
 
+        let Some(program) = context.get(SCAN_SOURCE_CONTEXT_KEY) else {
+            // Not one of ours.
+            return Ok(PluginUpdateLoop::NoUpdates);
+        };
+        // Labels of host scans are relative to their root, which is outside of the plugin's CWD.
+        let host_root = context.get(HOST_SCAN_ROOT_CONTEXT_KEY).map(PathBuf::from);
+        let source = match &host_root {
+            Some(root) => ScanSource::HostCrawler(root.clone()),
+            None => ScanSource::ExternalProgram(PathBuf::from(program)),
+        };
+        let repr = |repr: PathBuf| match &host_root {
+            Some(root) => root.join(repr).components().collect(),
+            None => repr,
+        };
+
         let Some(exitcode) = exitcode else {
+            self.context.fail_scan(source);
             return self
                 .context
                 .log_error(PluginError::FileSystemScanFailed(anyhow!(
@@ -339,6 +716,7 @@ impl PrimeHopperPlugin {
                 .into();
         };
         if exitcode != 0 {
+            self.context.fail_scan(source);
             let stderr = str::from_utf8(&stderr).unwrap_or("failed to decode program output");
             return self
                 .context
@@ -348,9 +726,29 @@ impl PrimeHopperPlugin {
                 .into();
         }
 
-        // Results must be passed through stdout as a RMP-serialized [BTreeMap<PathBuf, PathBuf>]
-        // structure.
-        let Ok(paths) = marshall_command::deserialize::<BTreeMap<PathBuf, PathBuf>>(&stdout) else {
+        // Results must be passed through stdout as a RMP-serialized
+        // [BTreeMap<PathBuf, (PathBuf, MarkerKind)>] structure, as written by
+        // `find-git-repositories`, or as a plain [BTreeMap<PathBuf, PathBuf>] structure.
+        let paths = match marshall_command::deserialize::<BTreeMap<PathBuf, (PathBuf, MarkerKind)>>(
+            &stdout,
+        ) {
+            Ok(paths) => Ok(paths
+                .into_iter()
+                .map(|(label, (path, marker))| {
+                    PathEntry::new(repr(label), path).with_marker(marker)
+                })
+                .collect::<Vec<_>>()),
+            Err(_) => {
+                marshall_command::deserialize::<BTreeMap<PathBuf, PathBuf>>(&stdout).map(|paths| {
+                    paths
+                        .into_iter()
+                        .map(|(label, path)| PathEntry::new(repr(label), path))
+                        .collect()
+                })
+            }
+        };
+        let Ok(paths) = paths else {
+            self.context.fail_scan(source);
             return self
                 .context
                 .log_error(PluginError::FileSystemScanFailed(anyhow!(
@@ -359,11 +757,7 @@ impl PrimeHopperPlugin {
                 .into();
         };
 
-        Ok(self.context.add_choices(
-            paths
-                .into_iter()
-                .map(|(repr, path)| PathEntry::new(repr, path)),
-        ))
+        Ok(self.context.complete_scan(source) | self.context.add_choices(paths.into_iter()))
     }
 
     fn handle_event(&mut self, event: Event) -> Result {
@@ -380,47 +774,216 @@ impl PrimeHopperPlugin {
             #[cfg(feature = "zellij_fallback_fs_api")]
             Event::FileSystemUpdate(paths) => self.handle_filesystem_update(paths),
             #[cfg(feature = "zellij_run_command_api")]
-            Event::RunCommandResult(exitcode, stdout, stderr, _context) => {
-                self.handle_external_pathfinder_command_result(exitcode, stdout, stderr)
+            Event::RunCommandResult(exitcode, _, stderr, context)
+                if context.contains_key(BACKGROUND_SESSION_CONTEXT_KEY) =>
+            {
+                self.handle_background_session_result(exitcode, stderr, context)
             }
-            Event::SessionUpdate(sessions, _) => {
+            #[cfg(feature = "zellij_run_command_api")]
+            Event::RunCommandResult(exitcode, _, stderr, context)
+                if context.contains_key(MIGRATED_SESSION_CONTEXT_KEY) =>
+            {
+                self.handle_session_migration_result(exitcode, stderr, context)
+            }
+            #[cfg(feature = "zellij_run_command_api")]
+            Event::RunCommandResult(exitcode, _, stderr, context)
+                if context.contains_key(RENAMED_SESSION_CONTEXT_KEY) =>
+            {
+                self.handle_session_rename_result(exitcode, stderr, context)
+            }
+            #[cfg(feature = "zellij_run_command_api")]
+            Event::RunCommandResult(exitcode, stdout, stderr, context) => {
+                self.handle_external_pathfinder_command_result(exitcode, stdout, stderr, context)
+            }
+            Event::Timer(_) => Ok(self.handle_timer()),
+            Event::SessionUpdate(sessions, resurrectable_sessions) => {
+                let session_entries =
+                    sessions
+                        .iter()
+                        .map(SessionEntry::running)
+                        .chain(resurrectable_sessions.iter().map(|(name, exited_for)| {
+                            SessionEntry::exited(name.clone(), *exited_for)
+                        }))
+                        .collect::<Vec<_>>();
                 self.all_sessions_name = sessions
                     .into_iter()
                     .inspect(|session| {
                         if session.is_current_session {
                             self.current_session_name = Some(session.name.clone());
+                            self.current_session_occupancy =
+                                SessionOccupancy::new(session, get_plugin_ids().plugin_id);
                         }
                     })
                     .map(|session| session.name)
                     .collect();
-                Ok(PluginUpdateLoop::NoUpdates)
+                Ok(self.context.set_sessions(
+                    self.current_session_name.clone(),
+                    self.all_sessions_name.clone(),
+                    resurrectable_sessions
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect(),
+                ) | self
+                    .context
+                    .set_session_entries(session_entries.into_iter()))
+            }
+            Event::Key(key) => self.handle_key(key),
+            _ => Ok(PluginUpdateLoop::NoUpdates),
+        }
+    }
+
+    /// Resolves `key` against the keymap, as the continuation of the pending key sequence if any.
+    fn handle_key(&mut self, key: KeyWithModifier) -> Result {
+        if self.context.session_prompt().is_some() {
+            return self.handle_session_prompt_key(key);
+        }
+
+        let mode = self.context.input_mode().unwrap_or_default();
+        self.pending_keys.push(key);
+        match self.config.keymap.resolve(mode, &self.pending_keys) {
+            KeyResolution::Action(action) => {
+                self.pending_keys.clear();
+                self.handle_action(action)
             }
+            KeyResolution::Pending => Ok(PluginUpdateLoop::NoUpdates),
+            KeyResolution::Unbound => {
+                let mut keys = std::mem::take(&mut self.pending_keys);
+                let key = keys.pop().expect("the pending key sequence is not empty");
+                // Abandon the pending sequence, and give its last key a chance on its own.
+                if !keys.is_empty() {
+                    return self.handle_key(key);
+                }
+                match key.bare_key {
+                    BareKey::Char(ch) if key.has_no_modifiers() && mode == InputMode::Insert => {
+                        self.context.on_user_input(ch).into()
+                    }
+                    _ => Ok(PluginUpdateLoop::NoUpdates),
+                }
+            }
+        }
+    }
+
+    fn handle_action(&mut self, action: Action) -> Result {
+        let picker = self.context.picker();
+        match action {
             // Clear reported errors on all user inputs.
             // NOTE: use the non-short-circuiting variant of the OR operator to force
             // evaluation of the rhs.
-            Event::Key(key) => match key.bare_key {
-                BareKey::Enter if key.has_no_modifiers() => {
-                    self.context.clear_errors() | self.submit()
+            Action::Select => match picker {
+                Picker::Paths => self.context.clear_errors() | self.submit(),
+                Picker::Sessions => self.context.clear_errors() | self.switch_to_selected_session(),
+            },
+            Action::SelectUp => self.context.select_up().into(),
+            Action::SelectDown => self.context.select_down().into(),
+            Action::SelectPageUp => self
+                .context
+                .select_page_up(self.renderer.page_size())
+                .into(),
+            Action::SelectPageDown => self
+                .context
+                .select_page_down(self.renderer.page_size())
+                .into(),
+            Action::SelectFirst => self.context.select_first().into(),
+            Action::SelectLast => self.context.select_last().into(),
+            Action::Clear => self
+                .context
+                .clear_user_input()
+                .or_else(|| self.terminate())
+                .into(),
+            Action::Quit => self.terminate().into(),
+            Action::SwitchMatcher => self.context.cycle_matcher_kind().into(),
+            Action::Edit(edit) => self.context.edit_user_input(edit).into(),
+            Action::EnterInsertMode => self.context.set_input_mode(InputMode::Insert).into(),
+            Action::EnterNormalMode => self.context.set_input_mode(InputMode::Normal).into(),
+            Action::TogglePicker => {
+                (self.context.clear_errors() | self.context.toggle_picker()).into()
+            }
+            // Actions on directories.
+            Action::Open(target) if picker == Picker::Paths => {
+                self.context.clear_errors() | self.open_selected(target)
+            }
+            Action::CopyPath if picker == Picker::Paths => {
+                self.context.clear_errors() | self.copy_selected_path()
+            }
+            Action::ToggleMark if picker == Picker::Paths => self.context.toggle_mark().into(),
+            Action::OpenMarked(target) if picker == Picker::Paths => {
+                self.context.clear_errors() | self.open_marked(target)
+            }
+            // Actions on sessions.
+            Action::KillSession if picker == Picker::Sessions => {
+                self.context.clear_errors() | self.prompt_kill_selected_session()
+            }
+            Action::RenameSession if picker == Picker::Sessions => {
+                self.context.clear_errors() | self.prompt_rename_selected_session()
+            }
+            Action::DetachSession if picker == Picker::Sessions => {
+                self.context.clear_errors() | self.detach_selected_session()
+            }
+            Action::Open(_)
+            | Action::CopyPath
+            | Action::ToggleMark
+            | Action::OpenMarked(_)
+            | Action::KillSession
+            | Action::RenameSession
+            | Action::DetachSession => Ok(PluginUpdateLoop::NoUpdates),
+        }
+    }
+
+    /// Handles `key` while the user is prompted for confirmation or input about a session.
+    fn handle_session_prompt_key(&mut self, key: KeyWithModifier) -> Result {
+        self.context.clear_errors();
+        match self.context.take_session_prompt() {
+            Some(SessionPrompt::ConfirmKill(session_name)) => {
+                // Any other key cancels.
+                if key.has_no_modifiers() && key.bare_key == BareKey::Char('y') {
+                    self.kill_session(&session_name);
                 }
-                BareKey::Up if key.has_no_modifiers() => self.context.select_up().into(),
-                BareKey::Down if key.has_no_modifiers() => self.context.select_down().into(),
-                BareKey::Esc if key.has_no_modifiers() => self
-                    .context
-                    .clear_user_input()
-                    .or_else(|| self.terminate())
-                    .into(),
-                BareKey::Backspace if key.has_no_modifiers() => {
-                    self.context.remove_trailing_char().into()
+                Ok(PluginUpdateLoop::MarkDirty)
+            }
+            Some(SessionPrompt::Rename {
+                session_name,
+                mut new_name,
+            }) => {
+                match self
+                    .config
+                    .keymap
+                    .resolve(InputMode::Insert, std::slice::from_ref(&key))
+                {
+                    KeyResolution::Action(Action::Select) => {
+                        return self.rename_session(session_name, new_name.as_str());
+                    }
+                    // The `vim` preset binds Esc to entering normal mode, which has no meaning here.
+                    KeyResolution::Action(
+                        Action::Clear | Action::Quit | Action::EnterNormalMode,
+                    ) => {
+                        return Ok(PluginUpdateLoop::MarkDirty);
+                    }
+                    KeyResolution::Action(Action::Edit(edit)) => _ = new_name.apply(edit),
+                    _ => match key.bare_key {
+                        BareKey::Char(ch) if key.has_no_modifiers() => new_name.insert(ch),
+                        _ => (),
+                    },
                 }
-                BareKey::Char('c') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
-                    self.terminate().into()
+                Ok(self.context.set_session_prompt(SessionPrompt::Rename {
+                    session_name,
+                    new_name,
+                }))
+            }
+            Some(SessionPrompt::ConfirmMigration {
+                legacy_name,
+                session_name,
+                relative_cwd,
+            }) => match key.bare_key {
+                BareKey::Char('y') if key.has_no_modifiers() => {
+                    self.migrate_session(legacy_name, session_name, relative_cwd)
                 }
-                BareKey::Char(ch) if key.has_no_modifiers() => {
-                    self.context.on_user_input(ch).into()
+                BareKey::Char('n') if key.has_no_modifiers() => {
+                    self.switch_to_legacy_session(legacy_name, relative_cwd)
                 }
-                _ => Ok(PluginUpdateLoop::NoUpdates),
+                // Any other key cancels.
+                _ => Ok(PluginUpdateLoop::MarkDirty),
             },
-            _ => Ok(PluginUpdateLoop::NoUpdates),
+            None => Ok(PluginUpdateLoop::NoUpdates),
         }
     }
 
@@ -434,13 +997,519 @@ impl PrimeHopperPlugin {
         let Some(selected) = self.context.selected_match() else {
             return Ok(PluginUpdateLoop::MarkDirty);
         };
-        self.safe_switch_session(selected.path())
+        let relative_cwd = selected.path();
+        let session_name = self.session_name(&relative_cwd)?;
+        self.switch_to_directory_session(session_name, relative_cwd)
+    }
+
+    /// Switches to `session_name`, the session of the directory at `relative_cwd` under the current
+    /// naming scheme. If it doesn't exist but a session was opened for the directory under an older
+    /// naming scheme, offers to rename that session instead of creating a new one.
+    fn switch_to_directory_session(
+        &mut self,
+        session_name: String,
+        relative_cwd: PathBuf,
+    ) -> Result {
+        // Sessions named after another directory, e.g. one that owned the name before a directory
+        // with a smaller path rendered to the same name was listed, are not reused.
+        let path = get_plugin_ids().initial_cwd.join(&relative_cwd);
+        let session_name = match self.context.has_session(&session_name)
+            && self.session_aliases.belongs_to_other(&path, &session_name)
+        {
+            true => hash::collision_fallback(&session_name, &relative_cwd)?,
+            false => session_name,
+        };
+        if !self.context.has_session(&session_name) {
+            if let Some(legacy_name) = self.legacy_session_name(&relative_cwd) {
+                // Exited sessions can't be renamed: resurrect them under their former name.
+                if !self.all_sessions_name.contains(&legacy_name) {
+                    return self.switch_to_legacy_session(legacy_name, relative_cwd);
+                }
+                return Ok(self
+                    .context
+                    .set_session_prompt(SessionPrompt::ConfirmMigration {
+                        legacy_name,
+                        session_name,
+                        relative_cwd,
+                    }));
+            }
+        }
+
+        self.record_session_alias(&relative_cwd, &session_name);
+        self.safe_switch_session(
+            session_name,
+            Some(self.config.layout.clone()),
+            &[relative_cwd],
+        )
     }
 
-    fn safe_switch_session(&mut self, relative_cwd: PathBuf) -> Result {
-        let session_name =
-            hash::get_session_name(&relative_cwd).with_context(|| "deriving the session name")?;
+    /// The name of an existing session opened for the directory at `relative_cwd` under an older
+    /// naming scheme, if any.
+    fn legacy_session_name(&self, relative_cwd: &Path) -> Option<String> {
+        let alias = self
+            .session_aliases
+            .get(&get_plugin_ids().initial_cwd.join(relative_cwd));
+        self.context.legacy_session_name(relative_cwd, alias)
+    }
 
+    /// Switches to `legacy_name`, keeping the former name of the session of `relative_cwd`.
+    fn switch_to_legacy_session(&mut self, legacy_name: String, relative_cwd: PathBuf) -> Result {
+        self.record_session_alias(&relative_cwd, &legacy_name);
+        self.safe_switch_session(legacy_name, None, &[relative_cwd])
+    }
+
+    /// Renames the running session `legacy_name` of `relative_cwd` to `session_name`, its name under
+    /// the current naming scheme, and switches to it.
+    fn migrate_session(
+        &mut self,
+        legacy_name: String,
+        session_name: String,
+        relative_cwd: PathBuf,
+    ) -> Result {
+        if self.current_session_name.as_ref() == Some(&legacy_name) {
+            rename_session(&session_name);
+            self.record_session_alias(&relative_cwd, &session_name);
+            return self.visit_and_close(&[relative_cwd]);
+        }
+        self.rename_other_session(legacy_name, session_name, relative_cwd)
+    }
+
+    /// Renames `legacy_name` to `session_name`. The switch happens once the session is renamed.
+    #[cfg(feature = "zellij_run_command_api")]
+    fn rename_other_session(
+        &mut self,
+        legacy_name: String,
+        session_name: String,
+        relative_cwd: PathBuf,
+    ) -> Result {
+        let relative_cwd = relative_cwd
+            .to_str()
+            .with_context(|| format!("failed to decode path (invalid UTF-8?): {relative_cwd:?}"))?;
+        run_rename_session_command(
+            &legacy_name,
+            &session_name,
+            BTreeMap::from([
+                (
+                    MIGRATED_SESSION_CONTEXT_KEY.to_string(),
+                    legacy_name.clone(),
+                ),
+                (SESSION_NAME_CONTEXT_KEY.to_string(), session_name.clone()),
+                (
+                    RELATIVE_CWD_CONTEXT_KEY.to_string(),
+                    relative_cwd.to_string(),
+                ),
+            ]),
+        );
+        Ok(PluginUpdateLoop::MarkDirty)
+    }
+
+    #[cfg(not(feature = "zellij_run_command_api"))]
+    fn rename_other_session(
+        &mut self,
+        legacy_name: String,
+        _session_name: String,
+        _relative_cwd: PathBuf,
+    ) -> Result {
+        self.context
+            .log_error(PluginError::SessionActionFailed {
+                action: "rename",
+                session_name: legacy_name,
+                reason: "renaming other sessions requires the `run_command` API",
+            })
+            .into()
+    }
+
+    #[cfg(feature = "zellij_run_command_api")]
+    fn handle_session_migration_result(
+        &mut self,
+        exitcode: Option<i32>,
+        stderr: Vec<u8>,
+        mut context: BTreeMap<String, String>,
+    ) -> Result {
+        let legacy_name = context
+            .remove(MIGRATED_SESSION_CONTEXT_KEY)
+            .unwrap_or_default();
+        if exitcode != Some(0) {
+            eprintln!(
+                "`zellij action rename-session` failed with exitcode {exitcode:?}: {:?}",
+                String::from_utf8_lossy(&stderr)
+            );
+            return self
+                .context
+                .log_error(PluginError::SessionActionFailed {
+                    action: "rename",
+                    session_name: legacy_name,
+                    reason: "`zellij action rename-session` failed",
+                })
+                .into();
+        }
+
+        let session_name = context.remove(SESSION_NAME_CONTEXT_KEY).unwrap_or_default();
+        let relative_cwd =
+            PathBuf::from(context.remove(RELATIVE_CWD_CONTEXT_KEY).unwrap_or_default());
+        self.record_session_alias(&relative_cwd, &session_name);
+        self.safe_switch_session(session_name, None, &[relative_cwd])
+    }
+
+    #[cfg(feature = "zellij_run_command_api")]
+    fn handle_session_rename_result(
+        &mut self,
+        exitcode: Option<i32>,
+        stderr: Vec<u8>,
+        mut context: BTreeMap<String, String>,
+    ) -> Result {
+        if exitcode == Some(0) {
+            // The session list is refreshed by the next `SessionUpdate`.
+            return Ok(PluginUpdateLoop::NoUpdates);
+        }
+        eprintln!(
+            "`zellij action rename-session` failed with exitcode {exitcode:?}: {:?}",
+            String::from_utf8_lossy(&stderr)
+        );
+        self.context
+            .log_error(PluginError::SessionActionFailed {
+                action: "rename",
+                session_name: context
+                    .remove(RENAMED_SESSION_CONTEXT_KEY)
+                    .unwrap_or_default(),
+                reason: "`zellij action rename-session` failed",
+            })
+            .into()
+    }
+
+    /// Remembers that the session of the directory at `relative_cwd` is named `session_name`.
+    fn record_session_alias(&mut self, relative_cwd: &Path, session_name: &str) {
+        let path = get_plugin_ids().initial_cwd.join(relative_cwd);
+        if !self.session_aliases.record(&path, session_name) {
+            return;
+        }
+        // Failing to persist aliases should not prevent opening entries.
+        if let Err(error) = self.session_aliases.save(&self.config.session_alias_store) {
+            eprintln!("failed to save session alias store: {error:?}");
+        }
+    }
+
+    /// Switches to the session selected in [Picker::Sessions], resurrecting it if it exited.
+    fn switch_to_selected_session(&mut self) -> Result {
+        let Some(session) = self.context.selected_session() else {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        };
+        let relative_cwds = session.path.iter().cloned().collect::<Vec<_>>();
+        self.safe_switch_session(session.name.clone(), None, &relative_cwds)
+    }
+
+    fn prompt_kill_selected_session(&mut self) -> Result {
+        let Some(session) = self.context.selected_session() else {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        };
+        if session.status == SessionStatus::Current {
+            return self
+                .context
+                .log_error(PluginError::SessionActionFailed {
+                    action: "kill",
+                    session_name: session.name.clone(),
+                    reason: "the plugin is running in this session",
+                })
+                .into();
+        }
+        Ok(self
+            .context
+            .set_session_prompt(SessionPrompt::ConfirmKill(session.name.clone())))
+    }
+
+    fn kill_session(&mut self, session_name: &str) {
+        if self.all_sessions_name.contains(session_name) {
+            kill_sessions(&[session_name]);
+        } else {
+            delete_dead_session(session_name);
+        }
+    }
+
+    fn prompt_rename_selected_session(&mut self) -> Result {
+        let Some(session) = self.context.selected_session() else {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        };
+        // Zellij only lets plugins rename the session they are running in, other sessions are
+        // renamed through its CLI.
+        if !cfg!(feature = "zellij_run_command_api") && session.status != SessionStatus::Current {
+            return self
+                .context
+                .log_error(PluginError::SessionActionFailed {
+                    action: "rename",
+                    session_name: session.name.clone(),
+                    reason: "renaming other sessions requires the `run_command` API",
+                })
+                .into();
+        }
+        Ok(self.context.set_session_prompt(SessionPrompt::Rename {
+            session_name: session.name.clone(),
+            new_name: LineEditor::default(),
+        }))
+    }
+
+    /// Renames `session_name` to `new_name`, with the characters Zellij rejects replaced.
+    fn rename_session(&mut self, session_name: String, new_name: &str) -> Result {
+        let new_name = hash::sanitize(new_name);
+        let reason = if new_name.is_empty() {
+            "the new name is empty"
+        } else if self.context.has_session(&new_name) {
+            "the new name is already taken"
+        } else if self.current_session_name.as_ref() == Some(&session_name) {
+            rename_session(&new_name);
+            return Ok(PluginUpdateLoop::MarkDirty);
+        } else {
+            return self.rename_session_through_cli(session_name, new_name);
+        };
+        self.context
+            .log_error(PluginError::SessionActionFailed {
+                action: "rename",
+                session_name,
+                reason,
+            })
+            .into()
+    }
+
+    #[cfg(feature = "zellij_run_command_api")]
+    fn rename_session_through_cli(&mut self, session_name: String, new_name: String) -> Result {
+        run_rename_session_command(
+            &session_name,
+            &new_name,
+            BTreeMap::from([(
+                RENAMED_SESSION_CONTEXT_KEY.to_string(),
+                session_name.clone(),
+            )]),
+        );
+        Ok(PluginUpdateLoop::MarkDirty)
+    }
+
+    #[cfg(not(feature = "zellij_run_command_api"))]
+    fn rename_session_through_cli(&mut self, session_name: String, _new_name: String) -> Result {
+        self.context
+            .log_error(PluginError::SessionActionFailed {
+                action: "rename",
+                session_name,
+                reason: "renaming other sessions requires the `run_command` API",
+            })
+            .into()
+    }
+
+    fn detach_selected_session(&mut self) -> Result {
+        let Some(session) = self.context.selected_session() else {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        };
+        // Zellij only lets plugins detach from the session they are running in.
+        if session.status != SessionStatus::Current {
+            return self
+                .context
+                .log_error(PluginError::SessionActionFailed {
+                    action: "detach from",
+                    session_name: session.name.clone(),
+                    reason: "only the current session can be detached from",
+                })
+                .into();
+        }
+        let update = self.terminate();
+        detach();
+        Ok(update)
+    }
+
+    /// Opens the selected entry in `target`, as an alternative to [Self::submit].
+    fn open_selected(&mut self, target: OpenTarget) -> Result {
+        let Some(selected) = self.context.selected_match() else {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        };
+        let relative_cwd = selected.path();
+        let cwd = get_plugin_ids().initial_cwd.join(&relative_cwd);
+        match target {
+            OpenTarget::ExistingSession => {
+                let session_name = self.session_name(&relative_cwd)?;
+                if self.context.session_badge(&selected).is_none()
+                    && self.legacy_session_name(&relative_cwd).is_none()
+                {
+                    return self
+                        .context
+                        .log_error(PluginError::SwitchSessionFailed {
+                            session_name,
+                            reason: "no existing session",
+                        })
+                        .into();
+                }
+                self.switch_to_directory_session(session_name, relative_cwd)
+            }
+            OpenTarget::FreshSession => {
+                let session_name = self
+                    .context
+                    .fresh_session_name(&self.session_name(&relative_cwd)?);
+                self.safe_switch_session(
+                    session_name,
+                    Some(self.config.layout.clone()),
+                    &[relative_cwd],
+                )
+            }
+            OpenTarget::Tab => {
+                new_tabs_with_layout(&tabs_layout(&[cwd])?);
+                self.visit_and_close(&[relative_cwd])
+            }
+            OpenTarget::FloatingPane => {
+                open_terminal_floating(cwd, None);
+                self.visit_and_close(&[relative_cwd])
+            }
+            OpenTarget::TiledPane => {
+                open_terminal(cwd);
+                self.visit_and_close(&[relative_cwd])
+            }
+        }
+    }
+
+    /// Opens all marked entries in `target`, or the selected entry if none is marked.
+    fn open_marked(&mut self, target: BatchTarget) -> Result {
+        let relative_cwds = self
+            .context
+            .take_marked()
+            .iter()
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        if relative_cwds.is_empty() {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        }
+
+        match target {
+            BatchTarget::BackgroundSessions => self.create_background_sessions(&relative_cwds),
+            BatchTarget::Tabs => {
+                // Name the session after the closest directory containing all entries, or after the
+                // directory the plugin runs in if they have none in common. The latter is not an
+                // entry of the plugin, so it is named after its basename instead of the template.
+                let root = common_ancestor(&relative_cwds);
+                let session_name = match root.file_name() {
+                    Some(_) => self.session_name(&root)?,
+                    None => {
+                        let initial_cwd = get_plugin_ids().initial_cwd;
+                        initial_cwd
+                            .file_name()
+                            .map(|basename| hash::sanitize(&basename.to_string_lossy()))
+                            .filter(|session_name| !session_name.is_empty())
+                            .with_context(|| {
+                                format!("failed to name session after directory: {initial_cwd:?}")
+                            })?
+                    }
+                };
+                let session_name = match self.context.has_session(&session_name) {
+                    true => self.context.fresh_session_name(&session_name),
+                    false => session_name,
+                };
+                let cwds = relative_cwds
+                    .iter()
+                    .map(|relative_cwd| get_plugin_ids().initial_cwd.join(relative_cwd))
+                    .collect::<Vec<_>>();
+                let layout = LayoutInfo::Stringified(tabs_layout(&cwds)?);
+                self.safe_switch_session(session_name, Some(layout), &relative_cwds)
+            }
+        }
+    }
+
+    /// Creates the session of each entry in `relative_cwds` that doesn't exist yet, without
+    /// switching to it. The plugin stays open: new sessions appear in the list as they start.
+    #[cfg(feature = "zellij_run_command_api")]
+    fn create_background_sessions(&mut self, relative_cwds: &[PathBuf]) -> Result {
+        // Zellij doesn't let plugins create sessions without switching to them, so we have to go
+        // through its CLI instead.
+        let mut options = vec![];
+        if let LayoutInfo::BuiltIn(layout) | LayoutInfo::File(layout) = &self.config.layout {
+            options.extend(["options", "--default-layout", layout]);
+        }
+
+        for relative_cwd in relative_cwds {
+            let session_name = self.session_name(relative_cwd)?;
+            if self.context.has_session(&session_name)
+                || self.legacy_session_name(relative_cwd).is_some()
+            {
+                continue;
+            }
+            let command = ["zellij", "attach", "--create-background", &session_name]
+                .into_iter()
+                .chain(options.iter().copied())
+                .collect::<Vec<_>>();
+            run_command_with_env_variables_and_cwd(
+                &command,
+                BTreeMap::new(),
+                get_plugin_ids().initial_cwd.join(relative_cwd),
+                BTreeMap::from([(
+                    BACKGROUND_SESSION_CONTEXT_KEY.to_string(),
+                    session_name.clone(),
+                )]),
+            );
+            self.context.record_visit(relative_cwd);
+        }
+
+        if let Err(error) = self.save_frecency() {
+            eprintln!("failed to save frecency store: {error:?}");
+        }
+
+        Ok(PluginUpdateLoop::MarkDirty)
+    }
+
+    #[cfg(not(feature = "zellij_run_command_api"))]
+    fn create_background_sessions(&mut self, _relative_cwds: &[PathBuf]) -> Result {
+        self.context
+            .log_error(PluginError::CreateSessionFailed {
+                session_name: String::new(),
+                reason: "background sessions require the `run_command` API".to_string(),
+            })
+            .into()
+    }
+
+    #[cfg(feature = "zellij_run_command_api")]
+    fn handle_background_session_result(
+        &mut self,
+        exitcode: Option<i32>,
+        stderr: Vec<u8>,
+        context: BTreeMap<String, String>,
+    ) -> Result {
+        if exitcode == Some(0) {
+            return Ok(PluginUpdateLoop::NoUpdates);
+        }
+
+        let session_name = context[BACKGROUND_SESSION_CONTEXT_KEY].clone();
+        let reason = match exitcode {
+            Some(exitcode) => format!(
+                "`zellij attach` failed with exitcode {exitcode}: {:?}",
+                String::from_utf8_lossy(&stderr)
+            ),
+            None => "`zellij attach` failed without exitcode (killed by signal?)".to_string(),
+        };
+        self.context
+            .log_error(PluginError::CreateSessionFailed {
+                session_name,
+                reason,
+            })
+            .into()
+    }
+
+    /// Copies the absolute path of the selected entry to the clipboard. The plugin stays open.
+    fn copy_selected_path(&mut self) -> Result {
+        let Some(selected) = self.context.selected_match() else {
+            return Ok(PluginUpdateLoop::MarkDirty);
+        };
+        let cwd = get_plugin_ids().initial_cwd.join(selected.path());
+        let path = cwd
+            .to_str()
+            .with_context(|| format!("failed to decode path (invalid UTF-8?): {cwd:?}"))?;
+        self.clipboard = Some(path.to_string());
+        Ok(PluginUpdateLoop::MarkDirty)
+    }
+
+    /// Switches to `session_name`, created with `layout` if it doesn't exist yet, and records a
+    /// visit to `relative_cwds`. The session starts in the first of `relative_cwds` if there is only
+    /// one, or in their closest common directory otherwise.
+    ///
+    /// Without `layout`, `session_name` must be the name of an existing session, which is resurrected
+    /// if it exited.
+    fn safe_switch_session(
+        &mut self,
+        session_name: String,
+        layout: Option<LayoutInfo>,
+        relative_cwds: &[PathBuf],
+    ) -> Result {
         // We have to wait for the `Event::SessionUpdate` event to get the list of existing
         // sessions as well as the name of the current session.
         // NOTE: we _could_ queue up the "switch session" action if the current session name is
@@ -457,8 +1526,8 @@ impl PrimeHopperPlugin {
                 .into();
         };
 
-        // TODO: this class of error should be prevented by filtering the current session out of
-        // the list, or by silently closing the plugin pane.
+        // The current session is filtered out of the list of candidates unless configured to be
+        // displayed with a badge.
         if *current_session_name == session_name {
             return self
                 .context
@@ -469,19 +1538,140 @@ impl PrimeHopperPlugin {
                 .into();
         }
 
-        // NOTE: We should have 2 options at this point:
-        // - take over current session (rename and change cwd): not currently possible due to the
-        //   lack of a Zellij API to change the cwd of a session.
-        // - switch session and kill previous one: uses a `bootstrap` flag passed at creation to
-        //   infer that this session was created only to run the plugin.
+        // NOTE: Taking over a bootstrap session (rename and change cwd) is not currently possible
+        // due to the lack of a Zellij API to change the cwd of a session. Instead, switch session
+        // and kill the bootstrap session, unless the user started working in it in the meantime.
+        let kept_reason = self
+            .config
+            .bootstrap
+            .then(|| self.current_session_occupancy.takeover_blocker())
+            .flatten();
+        let current_session_name = current_session_name.clone();
 
-        let cwd = get_plugin_ids().initial_cwd.join(relative_cwd);
-        switch_session_with_layout(Some(&session_name), self.config.layout.clone(), Some(cwd));
+        match layout {
+            Some(layout) => {
+                let cwd = get_plugin_ids()
+                    .initial_cwd
+                    .join(common_ancestor(relative_cwds));
+                switch_session_with_layout(Some(&session_name), layout, Some(cwd));
+            }
+            None => switch_session(Some(&session_name)),
+        }
 
-        if self.config.kill_after_switch {
-            kill_sessions(&[current_session_name]);
+        match kept_reason {
+            // Stay open to report why the session was kept when the user comes back to it.
+            Some(reason) => {
+                self.visit(relative_cwds);
+                Ok(self.context.log_error(PluginError::BootstrapSessionKept {
+                    session_name: current_session_name,
+                    reason,
+                }))
+            }
+            None => {
+                if self.config.bootstrap {
+                    kill_sessions(&[current_session_name]);
+                }
+                self.visit_and_close(relative_cwds)
+            }
         }
+    }
 
+    fn session_name(&self, relative_cwd: &Path) -> anyhow::Result<String> {
+        self.context
+            .session_name(relative_cwd)
+            .with_context(|| "deriving the session name")
+    }
+
+    /// Records a visit to `relative_cwds` and closes the plugin.
+    fn visit_and_close(&mut self, relative_cwds: &[PathBuf]) -> Result {
+        self.visit(relative_cwds);
         self.terminate().into()
     }
+
+    /// Records a visit to `relative_cwds`.
+    fn visit(&mut self, relative_cwds: &[PathBuf]) {
+        // Failing to persist visits should not prevent opening entries.
+        relative_cwds
+            .iter()
+            .for_each(|relative_cwd| self.context.record_visit(relative_cwd));
+        if let Err(error) = self.save_frecency() {
+            eprintln!("failed to save frecency store: {error:?}");
+        }
+    }
+}
+
+/// Renames `session_name` to `new_name` through Zellij's CLI, since plugins can only rename the
+/// session they are running in. The result is reported as a `RunCommandResult` with `context`.
+#[cfg(feature = "zellij_run_command_api")]
+fn run_rename_session_command(
+    session_name: &str,
+    new_name: &str,
+    context: BTreeMap<String, String>,
+) {
+    run_command_with_env_variables_and_cwd(
+        &[
+            "zellij",
+            "--session",
+            session_name,
+            "action",
+            "rename-session",
+            new_name,
+        ],
+        BTreeMap::new(),
+        get_plugin_ids().initial_cwd,
+        context,
+    );
+}
+
+/// The closest directory containing all `paths`, or `paths[0]` itself if there is only one.
+fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+    paths
+        .iter()
+        .map(|path| path.components().collect::<Vec<_>>())
+        .reduce(|ancestor, path| {
+            ancestor
+                .into_iter()
+                .zip(path)
+                .take_while(|(lhs, rhs)| lhs == rhs)
+                .map(|(component, _)| component)
+                .collect()
+        })
+        .map(|components| components.into_iter().collect())
+        .unwrap_or_default()
+}
+
+/// A layout opening a tab named after each of `cwds`, with the same bars as Zellij's default
+/// layout.
+fn tabs_layout(cwds: &[PathBuf]) -> anyhow::Result<String> {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let tabs = cwds
+        .iter()
+        .map(|cwd| {
+            let name = cwd
+                .file_name()
+                .and_then(|name| name.to_str())
+                .with_context(|| format!("invalid tab name: {cwd:?}"))?;
+            let cwd = cwd
+                .to_str()
+                .with_context(|| format!("failed to decode path (invalid UTF-8?): {cwd:?}"))?;
+            Ok(format!(
+                "    tab name=\"{}\" cwd=\"{}\" {{\n        pane\n    }}\n",
+                escape(name),
+                escape(cwd),
+            ))
+        })
+        .collect::<anyhow::Result<String>>()?;
+    Ok(format!(
+        r#"layout {{
+    default_tab_template {{
+        pane size=1 borderless=true {{
+            plugin location="zellij:tab-bar"
+        }}
+        children
+        pane size=2 borderless=true {{
+            plugin location="zellij:status-bar"
+        }}
+    }}
+{tabs}}}"#
+    ))
 }
@@ -1,8 +1,17 @@
 use crate::core::PluginError;
-
-use std::{collections::BTreeMap, path::PathBuf};
+use crate::frecency::DEFAULT_PRUNE_MAX_AGE_DAYS;
+use crate::fuzzy_search_context::CurrentSessionDisplay;
+use crate::hash::SessionNameTemplate;
+use crate::keymap::{parse_keys, Action, InputMode, Keymap, KeymapPreset};
+use crate::matcher::MatcherKind;
+use project_markers::{ExcludeSet, MarkerSet};
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 use zellij_tile::{
-    prelude::{LayoutInfo, PipeMessage, PipeSource},
+    prelude::{LayoutInfo, MessageToPlugin, PipeMessage, PipeSource},
     shim::get_plugin_ids,
 };
 
@@ -16,9 +25,90 @@ pub(super) struct PathFinderPluginConfig {
     /// key.
     pub(super) pipe_message: Option<PipeMessage>,
 
-    /// Whether to automatically kill the session after switching.
-    /// This is set to `true` in [PathFinderPluginConfig.load] if `pipe_message` is not `None`.
-    pub(super) kill_after_switch: bool,
+    /// Whether the plugin runs in a session created only to host it, which is then killed after
+    /// switching away from it, unless other panes or clients live in it. Defaults to `true` if
+    /// `pipe_message` is not `None`.
+    pub(super) bootstrap: bool,
+
+    /// The directories scanned on `scan_repository_root` messages that do not specify their own
+    /// list of roots. Defaults to the plugin's CWD.
+    pub(super) scan_roots: Vec<ScanRoot>,
+
+    /// The `find-git-repositories` executable scanning the roots outside of the plugin's CWD on the
+    /// host. Such roots are reported as errors if unset, which is the default.
+    pub(super) host_scan_program: Option<PathBuf>,
+
+    /// The entries identifying project roots while scanning. Defaults to the markers of the
+    /// supported VCS.
+    pub(super) markers: MarkerSet,
+
+    /// The directories to skip while scanning. Defaults to dependency and build caches, e.g.
+    /// `node_modules`.
+    pub(super) excludes: ExcludeSet,
+
+    /// Whether to keep scanning below project roots to find nested repositories, and list the
+    /// linked worktrees of Git repositories. Defaults to `false`.
+    pub(super) descend_into_repositories: bool,
+
+    /// Whether to scan symbolic links to directories. Defaults to `false`.
+    pub(super) follow_symlinks: bool,
+
+    /// Whether to reuse an already running instance of the plugin instead of stacking a new one.
+    /// Defaults to `true`.
+    pub(super) single_instance: bool,
+
+    /// How to display the entry associated with the current session. Defaults to hiding it.
+    pub(super) current_session_display: CurrentSessionDisplay,
+
+    /// Whether to rank directories with a running session higher. Defaults to `false`.
+    pub(super) boost_active_sessions: bool,
+
+    /// How to name the session of each directory. Defaults to a short hash of the path followed by
+    /// its basename.
+    pub(super) session_name_template: SessionNameTemplate,
+
+    /// Where to persist visit statistics, as seen from the plugin's filesystem. Defaults to the
+    /// plugin's cache directory.
+    pub(super) frecency_store: PathBuf,
+
+    /// Where to persist the session name of each directory, as seen from the plugin's filesystem.
+    /// Defaults to the plugin's cache directory.
+    pub(super) session_alias_store: PathBuf,
+
+    /// The matcher in use when the plugin starts. Defaults to skim's fuzzy matcher.
+    pub(super) matcher: MatcherKind,
+
+    /// The keys bound to each action. Defaults to the bindings of the `keymap` preset, overridden
+    /// by `bind_<action>` and `bind_normal_<action>` options.
+    pub(super) keymap: Keymap,
+}
+
+/// A directory to scan for repositories, along with the max depth at which to stop inspecting
+/// child directories.
+///
+/// Relative paths are resolved against the plugin's CWD, which is the only part of the filesystem
+/// the plugin has access to. A leading `~` stands for the home directory. Roots outside of the
+/// plugin's CWD, or under a home directory the plugin does not know, are scanned on the host by
+/// [PathFinderPluginConfig::host_scan_program].
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct ScanRoot {
+    pub(super) path: PathBuf,
+    pub(super) max_depth: usize,
+}
+
+impl ScanRoot {
+    pub(super) fn new(path: impl Into<PathBuf>, max_depth: usize) -> Self {
+        Self {
+            path: path.into(),
+            max_depth,
+        }
+    }
+}
+
+impl Default for ScanRoot {
+    fn default() -> Self {
+        Self::new(".", usize::MAX)
+    }
 }
 
 // Configuration.
@@ -26,14 +116,270 @@ pub(super) struct PathFinderPluginConfig {
 /// See https://zellij.dev/documentation/plugin-aliases.html?highlight=caller#a-note-about-cwd.
 const LAYOUT_OPTION: &'static str = "layout";
 
+/// List of directories to scan on `scan_repository_root` messages without payload. See
+/// [parse_scan_roots] for the expected format.
+const SCAN_ROOTS_OPTION: &'static str = "scan_roots";
+
+/// The path of `find-git-repositories` on the host, scanning the roots the plugin cannot access.
+const HOST_SCAN_PROGRAM_OPTION: &str = "host_scan_program";
+
+/// Additional entries identifying project roots, e.g. `Cargo.toml,flake.nix`. See
+/// [MarkerSet::with_project_markers] for the expected format.
+const PROJECT_MARKERS_OPTION: &'static str = "project_markers";
+
+/// Additional directories to skip while scanning, e.g. `vendor/,!target/`. See
+/// [ExcludeSet::with_patterns] for the expected format.
+const EXCLUDE_OPTION: &'static str = "exclude";
+
+/// Whether to also skip the directories listed in `.gitignore` and `.ignore` files. Either `true`
+/// or `false`.
+const RESPECT_IGNORE_FILES_OPTION: &'static str = "respect_ignore_files";
+
+/// Whether to keep scanning below project roots, and list linked worktrees. Either `true` or
+/// `false`.
+const DESCEND_INTO_REPOSITORIES_OPTION: &'static str = "descend_into_repositories";
+
+/// Whether to scan symbolic links to directories. Either `true` or `false`.
+const FOLLOW_SYMLINKS_OPTION: &'static str = "follow_symlinks";
+
+/// Whether the plugin runs in a session created only to host it. Either `true` or `false`.
+const BOOTSTRAP_OPTION: &'static str = "bootstrap";
+
+/// Whether to reuse an already running instance of the plugin. Either `true` or `false`.
+const SINGLE_INSTANCE_OPTION: &'static str = "single_instance";
+
+/// How to display the entry associated with the current session. Either `hide` or `mark`.
+const CURRENT_SESSION_OPTION: &'static str = "current_session";
+
+/// Whether to rank directories with a running session higher. Either `true` or `false`.
+const BOOST_ACTIVE_SESSIONS_OPTION: &'static str = "boost_active_sessions";
+
+/// How to name the session of each directory, e.g. `{parent}/{basename}`. See
+/// [SessionNameTemplate::parse] for the expected format.
+const SESSION_NAME_TEMPLATE_OPTION: &'static str = "session_name_template";
+
+/// Where to persist visit statistics. Note that plugins only have access to the `/host` (the
+/// plugin's CWD), `/data`, `/cache` and `/tmp` directories.
+const FRECENCY_STORE_OPTION: &'static str = "frecency_store";
+
+/// The default location of the visit statistics, which persists across sessions.
+const DEFAULT_FRECENCY_STORE: &'static str = "/cache/frecency.json";
+
+/// Where to persist the session name of each directory, to find sessions created under older
+/// naming schemes.
+const SESSION_ALIAS_STORE_OPTION: &'static str = "session_alias_store";
+
+/// The default location of the session names, which persists across sessions.
+const DEFAULT_SESSION_ALIAS_STORE: &'static str = "/cache/session_aliases.json";
+
+/// The matcher in use when the plugin starts. One of `skim`, `clangd`, `exact`, `prefix` or
+/// `regex`.
+const MATCHER_OPTION: &'static str = "matcher";
+
+/// Prefix of the options binding keys to actions, e.g. `bind_down "Down, Ctrl n"`. See
+/// [parse_keys] for the expected format.
+const BIND_OPTION_PREFIX: &'static str = "bind_";
+
+/// Prefix of the options binding keys to actions in the normal mode of modal keymaps, e.g.
+/// `bind_normal_down "j"`.
+const BIND_NORMAL_OPTION_PREFIX: &'static str = "bind_normal_";
+
+/// The built-in set of key bindings. One of `default`, `readline` or `vim`.
+const KEYMAP_OPTION: &'static str = "keymap";
+
 impl PathFinderPluginConfig {
-    pub(super) fn load(&mut self, configuration: &BTreeMap<String, String>) {
+    /// Loads the plugin configuration. Invalid options are replaced with their default value and
+    /// reported back to the caller.
+    pub(super) fn load(&mut self, configuration: &BTreeMap<String, String>) -> Vec<PluginError> {
+        let mut errors = Vec::new();
+
         self.layout = parse_layout(&configuration.get(LAYOUT_OPTION));
         self.pipe_message = synthesize_pipe_message(configuration);
-        self.kill_after_switch = self.pipe_message.is_some();
+        self.bootstrap = parse_bool(
+            configuration,
+            BOOTSTRAP_OPTION,
+            self.pipe_message.is_some(),
+            &mut errors,
+        );
+        self.scan_roots = match configuration.get(SCAN_ROOTS_OPTION) {
+            Some(spec) => parse_scan_roots(spec).unwrap_or_else(|reason| {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!("{SCAN_ROOTS_OPTION}: {reason}"),
+                });
+                vec![ScanRoot::default()]
+            }),
+            None => vec![ScanRoot::default()],
+        };
+        self.markers = match configuration.get(PROJECT_MARKERS_OPTION) {
+            Some(spec) => MarkerSet::with_project_markers(spec).unwrap_or_else(|reason| {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!("{PROJECT_MARKERS_OPTION}: {reason}"),
+                });
+                MarkerSet::default()
+            }),
+            None => MarkerSet::default(),
+        };
+        let respect_ignore_files = parse_bool(
+            configuration,
+            RESPECT_IGNORE_FILES_OPTION,
+            false,
+            &mut errors,
+        );
+        self.excludes = ExcludeSet::with_patterns(
+            configuration.get(EXCLUDE_OPTION).map_or("", String::as_str),
+            respect_ignore_files,
+        )
+        .unwrap_or_else(|reason| {
+            errors.push(PluginError::ConfigurationError {
+                reason: format!("{EXCLUDE_OPTION}: {reason}"),
+            });
+            ExcludeSet::with_patterns("", respect_ignore_files)
+                .expect("an empty list of patterns is valid")
+        });
+        self.descend_into_repositories = parse_bool(
+            configuration,
+            DESCEND_INTO_REPOSITORIES_OPTION,
+            false,
+            &mut errors,
+        );
+        self.follow_symlinks =
+            parse_bool(configuration, FOLLOW_SYMLINKS_OPTION, false, &mut errors);
+        self.single_instance = parse_bool(configuration, SINGLE_INSTANCE_OPTION, true, &mut errors);
+        self.boost_active_sessions = parse_bool(
+            configuration,
+            BOOST_ACTIVE_SESSIONS_OPTION,
+            false,
+            &mut errors,
+        );
+        self.session_name_template = match configuration.get(SESSION_NAME_TEMPLATE_OPTION) {
+            Some(template) => SessionNameTemplate::parse(template).unwrap_or_else(|error| {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!("{SESSION_NAME_TEMPLATE_OPTION}: {error}"),
+                });
+                SessionNameTemplate::default()
+            }),
+            None => SessionNameTemplate::default(),
+        };
+        self.host_scan_program = configuration
+            .get(HOST_SCAN_PROGRAM_OPTION)
+            .map(PathBuf::from);
+        self.frecency_store = configuration
+            .get(FRECENCY_STORE_OPTION)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_FRECENCY_STORE));
+        self.session_alias_store = configuration
+            .get(SESSION_ALIAS_STORE_OPTION)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SESSION_ALIAS_STORE));
+        self.current_session_display = match configuration.get(CURRENT_SESSION_OPTION) {
+            Some(value) => parse_current_session_display(value).unwrap_or_else(|| {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!("{CURRENT_SESSION_OPTION}: expected `hide` or `mark`: {value}"),
+                });
+                CurrentSessionDisplay::default()
+            }),
+            None => CurrentSessionDisplay::default(),
+        };
+        self.matcher = match configuration.get(MATCHER_OPTION) {
+            Some(value) => MatcherKind::parse(value).unwrap_or_else(|| {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!(
+                        "{MATCHER_OPTION}: expected `skim`, `clangd`, `exact`, `prefix` or `regex`: {value}"
+                    ),
+                });
+                MatcherKind::default()
+            }),
+            None => MatcherKind::default(),
+        };
+        let preset = match configuration.get(KEYMAP_OPTION) {
+            Some(value) => KeymapPreset::parse(value).unwrap_or_else(|| {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!(
+                        "{KEYMAP_OPTION}: expected `default`, `readline` or `vim`: {value}"
+                    ),
+                });
+                KeymapPreset::default()
+            }),
+            None => KeymapPreset::default(),
+        };
+        self.keymap = Keymap::new(preset);
+        for (key, spec) in configuration {
+            let (mode, name) = match key.strip_prefix(BIND_NORMAL_OPTION_PREFIX) {
+                Some(name) => (InputMode::Normal, name),
+                None => match key.strip_prefix(BIND_OPTION_PREFIX) {
+                    Some(name) => (InputMode::Insert, name),
+                    None => continue,
+                },
+            };
+            let Some(action) = Action::parse(name) else {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!("{key}: unknown action `{name}`"),
+                });
+                continue;
+            };
+            if mode == InputMode::Normal && !self.keymap.is_modal() {
+                errors.push(PluginError::ConfigurationError {
+                    reason: format!("{key}: only available with the `vim` keymap"),
+                });
+                continue;
+            }
+            match parse_keys(spec) {
+                Ok(keys) => self.keymap.bind(mode, action, keys),
+                Err(reason) => errors.push(PluginError::ConfigurationError {
+                    reason: format!("{key}: {reason}"),
+                }),
+            }
+        }
+
+        errors
+    }
+}
+
+/// Parses the boolean option `key`, falling back to `default` if it is missing or invalid.
+fn parse_bool(
+    configuration: &BTreeMap<String, String>,
+    key: &str,
+    default: bool,
+    errors: &mut Vec<PluginError>,
+) -> bool {
+    let Some(value) = configuration.get(key) else {
+        return default;
+    };
+
+    value.parse::<bool>().unwrap_or_else(|_| {
+        errors.push(PluginError::ConfigurationError {
+            reason: format!("{key}: invalid bool value: {value}"),
+        });
+        default
+    })
+}
+
+fn parse_current_session_display(value: &str) -> Option<CurrentSessionDisplay> {
+    match value {
+        "hide" => Some(CurrentSessionDisplay::Hide),
+        "mark" => Some(CurrentSessionDisplay::Mark),
+        _ => None,
     }
 }
 
+/// Parses a list of scan roots.
+///
+/// Roots are separated by a colon (`:`), and each root may specify its own max depth with a `=`
+/// suffix, e.g. `src=3:work:opt/checkouts=2`. Roots without explicit max depth are scanned
+/// entirely.
+fn parse_scan_roots(spec: &str) -> Result<Vec<ScanRoot>, String> {
+    spec.split(':')
+        .filter(|root| !root.is_empty())
+        .map(|root| match root.rsplit_once('=') {
+            Some((path, max_depth)) => max_depth
+                .parse::<usize>()
+                .map(|max_depth| ScanRoot::new(path, max_depth))
+                .map_err(|_| format!("invalid usize value: {max_depth}")),
+            None => Ok(ScanRoot::new(root, usize::MAX)),
+        })
+        .collect()
+}
+
 /// The default builtin layout to use if the configuration does not specify one.
 const DEFAULT_BUILTIN_LAYOUT: &'static str = "default";
 
@@ -82,7 +428,21 @@ impl Default for PathFinderPluginConfig {
         Self {
             layout: LayoutInfo::BuiltIn("default".to_string()),
             pipe_message: Default::default(),
-            kill_after_switch: false,
+            bootstrap: false,
+            scan_roots: vec![ScanRoot::default()],
+            host_scan_program: None,
+            markers: MarkerSet::default(),
+            excludes: ExcludeSet::default(),
+            descend_into_repositories: false,
+            follow_symlinks: false,
+            single_instance: true,
+            current_session_display: CurrentSessionDisplay::default(),
+            boost_active_sessions: false,
+            session_name_template: SessionNameTemplate::default(),
+            frecency_store: PathBuf::from(DEFAULT_FRECENCY_STORE),
+            session_alias_store: PathBuf::from(DEFAULT_SESSION_ALIAS_STORE),
+            matcher: MatcherKind::default(),
+            keymap: Keymap::default(),
         }
     }
 }
@@ -100,6 +460,20 @@ impl Default for PathFinderPluginConfig {
 /// }
 /// ```
 ///
+/// The optional payload is either a max depth at which to stop scanning the plugin's CWD, or a list
+/// of roots (relative to the plugin's CWD) in the format accepted by the `scan_roots` option:
+///
+/// ```kdl
+/// MessagePlugin "pathfinder" {
+///   cwd "/home/user"
+///   startup_message_name "scan_repository_root"
+///   startup_message_payload "src=3:work"
+///   launch_new true
+/// }
+/// ```
+///
+/// Without payload, the roots from the `scan_roots` option are scanned.
+///
 /// Note that `launch_new` is required to guarantee that the plugin is restarted with the correct
 /// CWD: Zellij plugins are jailed under their CWD, and cannot access the filesystem beyond it.
 const PATHFINDER_COMMAND_SCAN_REPOSITORY_ROOT: &'static str = "scan_repository_root";
@@ -116,11 +490,58 @@ const PATHFINDER_COMMAND_SCAN_REPOSITORY_ROOT: &'static str = "scan_repository_r
 /// ```
 const PATHFINDER_COMMAND_RUN_EXTERNAL_PROGRAM: &'static str = "run_external_program";
 
+/// The message name to pass to maintain the visit statistics used to rank directories. The payload
+/// is either `reset` to forget all visits, or `prune` to forget directories not visited for 90 days
+/// (or for the number of days specified as `prune=<days>`).
+///
+/// ```sh
+/// zellij pipe --plugin <plugin-url> --name frecency -- prune=30
+/// ```
+const PATHFINDER_COMMAND_FRECENCY: &'static str = "frecency";
+
+/// Broadcast by a new instance of the plugin to announce itself to already running instances. The
+/// payload is the CWD of the new instance.
+///
+/// Plugins are launched with `launch_new` to force their CWD to be updated, which would otherwise
+/// stack a new plugin pane every time the plugin is invoked.
+const PATHFINDER_COMMAND_ANNOUNCE_INSTANCE: &'static str = "prime_hopper_announce_instance";
+
+/// Sent back by a running instance of the plugin to a new instance it supersedes, i.e. when both
+/// share the same CWD.
+const PATHFINDER_COMMAND_INSTANCE_RUNNING: &'static str = "prime_hopper_instance_running";
+
+/// Builds the message announcing this instance of the plugin to all running plugins.
+pub(super) fn announce_instance_message(cwd: &Path) -> MessageToPlugin {
+    MessageToPlugin::new(PATHFINDER_COMMAND_ANNOUNCE_INSTANCE).with_payload(cwd.to_string_lossy())
+}
+
+/// Builds the message notifying the instance `plugin_id` that it is superseded by this instance.
+pub(super) fn instance_running_message(plugin_id: u32) -> MessageToPlugin {
+    MessageToPlugin::new(PATHFINDER_COMMAND_INSTANCE_RUNNING).with_destination_plugin_id(plugin_id)
+}
+
+#[derive(Debug)]
+pub(super) enum FrecencyCommand {
+    Reset,
+    Prune { max_age_days: u64 },
+}
+
 #[derive(Debug)]
 pub(super) enum PathFinderPluginCommand {
     PluginCommandError(PluginError),
-    ScanRepositoryRoot { max_depth: usize },
-    RunExternalProgram { programs: Vec<PathBuf> },
+    /// An empty list of roots stands for the roots configured through the `scan_roots` option.
+    ScanRepositoryRoot {
+        roots: Vec<ScanRoot>,
+    },
+    RunExternalProgram {
+        programs: Vec<PathBuf>,
+    },
+    Frecency(FrecencyCommand),
+    AnnounceInstance {
+        plugin_id: u32,
+        cwd: PathBuf,
+    },
+    InstanceRunning,
 }
 
 impl From<PipeMessage> for PathFinderPluginCommand {
@@ -132,6 +553,11 @@ impl From<PipeMessage> for PathFinderPluginCommand {
             PATHFINDER_COMMAND_RUN_EXTERNAL_PROGRAM => {
                 parse_run_external_program_payload(message.name, message.payload)
             }
+            PATHFINDER_COMMAND_FRECENCY => parse_frecency_payload(message.name, message.payload),
+            PATHFINDER_COMMAND_ANNOUNCE_INSTANCE => {
+                parse_announce_instance_payload(message.name, message.source, message.payload)
+            }
+            PATHFINDER_COMMAND_INSTANCE_RUNNING => PathFinderPluginCommand::InstanceRunning,
             _ => PathFinderPluginCommand::PluginCommandError(PluginError::UnknownPipeMessageError(
                 message.name,
             )),
@@ -144,18 +570,24 @@ fn parse_scan_repository_root_payload(
     payload: Option<String>,
 ) -> PathFinderPluginCommand {
     let Some(payload) = payload else {
-        return PathFinderPluginCommand::ScanRepositoryRoot {
-            max_depth: usize::MAX,
-        };
+        return PathFinderPluginCommand::ScanRepositoryRoot { roots: Vec::new() };
     };
 
-    let Ok(max_depth) = payload.parse::<usize>() else {
-        return PathFinderPluginCommand::PluginCommandError(PluginError::ConfigurationError {
-            reason: format!("{name}: invalid usize value: {payload}"),
-        });
-    };
+    // A bare number is the max depth at which to scan the plugin's CWD.
+    if let Ok(max_depth) = payload.parse::<usize>() {
+        return PathFinderPluginCommand::ScanRepositoryRoot {
+            roots: vec![ScanRoot::new(".", max_depth)],
+        };
+    }
 
-    PathFinderPluginCommand::ScanRepositoryRoot { max_depth }
+    match parse_scan_roots(&payload) {
+        Ok(roots) => PathFinderPluginCommand::ScanRepositoryRoot { roots },
+        Err(reason) => {
+            PathFinderPluginCommand::PluginCommandError(PluginError::ConfigurationError {
+                reason: format!("{name}: {reason}"),
+            })
+        }
+    }
 }
 
 fn parse_run_external_program_payload(
@@ -173,10 +605,186 @@ fn parse_run_external_program_payload(
     PathFinderPluginCommand::RunExternalProgram { programs }
 }
 
+fn parse_frecency_payload(name: String, payload: Option<String>) -> PathFinderPluginCommand {
+    let Some(payload) = payload else {
+        return PathFinderPluginCommand::PluginCommandError(
+            PluginError::MissingPipeMessagePayloadError(name),
+        );
+    };
+
+    match payload.split_once('=') {
+        None if payload == "reset" => PathFinderPluginCommand::Frecency(FrecencyCommand::Reset),
+        None if payload == "prune" => PathFinderPluginCommand::Frecency(FrecencyCommand::Prune {
+            max_age_days: DEFAULT_PRUNE_MAX_AGE_DAYS,
+        }),
+        Some(("prune", max_age_days)) => match max_age_days.parse::<u64>() {
+            Ok(max_age_days) => {
+                PathFinderPluginCommand::Frecency(FrecencyCommand::Prune { max_age_days })
+            }
+            Err(_) => PathFinderPluginCommand::PluginCommandError(
+                PluginError::InvalidPipeMessagePayloadError(format!(
+                    "{name}: invalid u64 value: {max_age_days}"
+                )),
+            ),
+        },
+        _ => PathFinderPluginCommand::PluginCommandError(
+            PluginError::InvalidPipeMessagePayloadError(format!("{name}: {payload}")),
+        ),
+    }
+}
+
+fn parse_announce_instance_payload(
+    name: String,
+    source: PipeSource,
+    payload: Option<String>,
+) -> PathFinderPluginCommand {
+    let PipeSource::Plugin(plugin_id) = source else {
+        return PathFinderPluginCommand::PluginCommandError(
+            PluginError::InvalidPipeMessagePayloadError(format!("{name}: not sent by a plugin")),
+        );
+    };
+    let Some(payload) = payload else {
+        return PathFinderPluginCommand::PluginCommandError(
+            PluginError::MissingPipeMessagePayloadError(name),
+        );
+    };
+
+    PathFinderPluginCommand::AnnounceInstance {
+        plugin_id,
+        cwd: PathBuf::from(payload),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn scan_repository_root_message(payload: Option<&str>) -> PipeMessage {
+        PipeMessage {
+            source: PipeSource::Plugin(0),
+            name: PATHFINDER_COMMAND_SCAN_REPOSITORY_ROOT.to_string(),
+            payload: payload.map(str::to_string),
+            args: Default::default(),
+            is_private: true,
+        }
+    }
+
+    #[test]
+    fn parse_scan_repository_root_payload_no_payload() {
+        let command = PathFinderPluginCommand::from(scan_repository_root_message(None));
+
+        assert!(matches!(
+            command,
+            PathFinderPluginCommand::ScanRepositoryRoot { roots } if roots.is_empty()
+        ));
+    }
+
+    #[test]
+    fn parse_scan_repository_root_payload_max_depth() {
+        let command = PathFinderPluginCommand::from(scan_repository_root_message(Some("3")));
+
+        assert!(matches!(
+            command,
+            PathFinderPluginCommand::ScanRepositoryRoot { roots } if roots == vec![ScanRoot::new(".", 3)]
+        ));
+    }
+
+    #[test]
+    fn parse_scan_repository_root_payload_multiple_roots() {
+        let command =
+            PathFinderPluginCommand::from(scan_repository_root_message(Some("src=3:work")));
+
+        assert!(matches!(
+            command,
+            PathFinderPluginCommand::ScanRepositoryRoot { roots } if roots == vec![
+                ScanRoot::new("src", 3),
+                ScanRoot::new("work", usize::MAX),
+            ]
+        ));
+    }
+
+    #[test]
+    fn parse_scan_repository_root_payload_invalid_max_depth() {
+        let command = PathFinderPluginCommand::from(scan_repository_root_message(Some("src=deep")));
+
+        assert!(matches!(
+            command,
+            PathFinderPluginCommand::PluginCommandError(PluginError::ConfigurationError { .. })
+        ));
+    }
+
+    fn frecency_message(payload: &str) -> PipeMessage {
+        PipeMessage {
+            source: PipeSource::Cli("cli".to_string()),
+            name: PATHFINDER_COMMAND_FRECENCY.to_string(),
+            payload: Some(payload.to_string()),
+            args: Default::default(),
+            is_private: true,
+        }
+    }
+
+    #[test]
+    fn parse_frecency_payload() {
+        assert!(matches!(
+            PathFinderPluginCommand::from(frecency_message("reset")),
+            PathFinderPluginCommand::Frecency(FrecencyCommand::Reset)
+        ));
+        assert!(matches!(
+            PathFinderPluginCommand::from(frecency_message("prune")),
+            PathFinderPluginCommand::Frecency(FrecencyCommand::Prune {
+                max_age_days: DEFAULT_PRUNE_MAX_AGE_DAYS
+            })
+        ));
+        assert!(matches!(
+            PathFinderPluginCommand::from(frecency_message("prune=30")),
+            PathFinderPluginCommand::Frecency(FrecencyCommand::Prune { max_age_days: 30 })
+        ));
+        assert!(matches!(
+            PathFinderPluginCommand::from(frecency_message("forget")),
+            PathFinderPluginCommand::PluginCommandError(
+                PluginError::InvalidPipeMessagePayloadError(_)
+            )
+        ));
+    }
+
+    #[test]
+    fn parse_announce_instance_payload() {
+        let message = PipeMessage {
+            source: PipeSource::Plugin(42),
+            name: PATHFINDER_COMMAND_ANNOUNCE_INSTANCE.to_string(),
+            payload: Some("/path/to/cwd".to_string()),
+            args: Default::default(),
+            is_private: false,
+        };
+
+        let command = PathFinderPluginCommand::from(message);
+
+        assert!(matches!(
+            command,
+            PathFinderPluginCommand::AnnounceInstance { plugin_id: 42, cwd } if cwd == Path::new("/path/to/cwd")
+        ));
+    }
+
+    #[test]
+    fn parse_announce_instance_payload_not_from_plugin() {
+        let message = PipeMessage {
+            source: PipeSource::Keybind,
+            name: PATHFINDER_COMMAND_ANNOUNCE_INSTANCE.to_string(),
+            payload: Some("/path/to/cwd".to_string()),
+            args: Default::default(),
+            is_private: false,
+        };
+
+        let command = PathFinderPluginCommand::from(message);
+
+        assert!(matches!(
+            command,
+            PathFinderPluginCommand::PluginCommandError(
+                PluginError::InvalidPipeMessagePayloadError(_)
+            )
+        ));
+    }
+
     #[test]
     fn parse_run_external_program_payload_no_payload() {
         let message = PipeMessage {
@@ -0,0 +1,95 @@
+/// Persisted session names of directories, to find the sessions created under older naming schemes.
+use crate::json_store;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+/// The name of the session last switched to for each directory, keyed by absolute path.
+///
+/// Session names are derived from paths relative to the plugin's CWD and from the configured
+/// template, so changing either orphans existing sessions. Absolute paths are stable across both.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct SessionAliasStore {
+    aliases: BTreeMap<PathBuf, String>,
+}
+
+impl SessionAliasStore {
+    /// Loads the store from `path`. A missing file yields an empty store.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        json_store::load(path, "session alias")
+    }
+
+    /// Writes the store to `path`, creating parent directories as needed.
+    pub(crate) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        json_store::save(self, path, "session alias")
+    }
+
+    /// Records that the session of the directory at `path` is named `session_name`. Returns
+    /// whether the store changed.
+    pub(crate) fn record(&mut self, path: &Path, session_name: &str) -> bool {
+        let path = normalize(path);
+        if self.aliases.get(&path).map(String::as_str) == Some(session_name) {
+            return false;
+        }
+        self.aliases.insert(path, session_name.to_string());
+        true
+    }
+
+    /// The name of the session last switched to for the directory at `path`, if any.
+    pub(crate) fn get(&self, path: &Path) -> Option<&str> {
+        self.aliases.get(&normalize(path)).map(String::as_str)
+    }
+
+    /// Whether the session named `session_name` was last switched to for a directory other than
+    /// the one at `path`.
+    pub(crate) fn belongs_to_other(&self, path: &Path, session_name: &str) -> bool {
+        let path = normalize(path);
+        self.aliases
+            .iter()
+            .any(|(other, alias)| alias == session_name && *other != path)
+    }
+}
+
+/// Lexically normalizes `path`, e.g. `/src/./nix-config/` to `/src/nix-config`. Symbolic links are
+/// not resolved since the plugin cannot see the host filesystem outside of its CWD.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if normalized.file_name().is_some() => _ = normalized.pop(),
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_normalizes_paths() {
+        let mut store = SessionAliasStore::default();
+
+        assert!(store.record(Path::new("/src/./nix-config/"), "nix-config"));
+        assert!(!store.record(Path::new("/src/work/../nix-config"), "nix-config"));
+        assert_eq!(store.get(Path::new("/src/nix-config")), Some("nix-config"));
+
+        assert!(store.record(Path::new("/src/nix-config"), "dotfiles"));
+        assert_eq!(store.get(Path::new("/src/nix-config")), Some("dotfiles"));
+        assert_eq!(store.get(Path::new("/src")), None);
+    }
+
+    #[test]
+    fn belongs_to_other_compares_normalized_paths() {
+        let mut store = SessionAliasStore::default();
+        store.record(Path::new("/work/api"), "api");
+
+        assert!(!store.belongs_to_other(Path::new("/work/./api"), "api"));
+        assert!(store.belongs_to_other(Path::new("/perso/api"), "api"));
+        assert!(!store.belongs_to_other(Path::new("/perso/api"), "web"));
+    }
+}
@@ -1,10 +1,19 @@
 use super::{
-    styles::{ControlBar, ControlSegment},
+    styles::{Annotations, ControlBar, ControlSegment},
     Frame,
 };
-use std::fmt::{Display, Formatter, Result};
+use crate::fuzzy_search_context::{
+    PathEntry, Picker, SessionBadge, SessionEntry, SessionPrompt, SessionStatus,
+};
+use crate::keymap::{display_key_sequence, Action, BatchTarget, InputMode, Keymap, OpenTarget};
+use project_markers::MarkerKind;
+use std::{
+    fmt::{Display, Formatter, Result},
+    time::Duration,
+};
 
 const SEARCH_PREFIX: &'static str = ">";
+const SESSIONS_SEARCH_PREFIX: &'static str = "sessions>";
 
 /// Takes into account the following constantly visble lines:
 ///   - At the top, the first 2 lines:
@@ -13,61 +22,200 @@ const SEARCH_PREFIX: &'static str = ">";
 ///   - At the bottom, the last 2 lines:
 ///     - Tips
 ///     - Status line
-const CHROME_LINE_COUNT: usize = 4;
-
-const CONTROL_BAR: ControlBar = ControlBar {
-    segments: [
-        ControlSegment {
-            control: "↓↑",
-            short_label: "Navigate",
-            full_label: "Navigate between entries",
-        },
-        ControlSegment {
-            control: "ENTER",
-            short_label: "Select",
-            full_label: "Select entry",
-        },
-        ControlSegment {
-            control: "ESC",
-            short_label: "Clear",
-            full_label: "Clear input",
-        },
-    ],
-};
+pub(super) const CHROME_LINE_COUNT: usize = 4;
+
+/// The actions displayed in the control bar, along with their short and full labels. Actions that
+/// aren't bound to any key are omitted.
+///
+/// Segments are sorted by decreasing importance, so that the last ones are dropped first on narrow
+/// screens.
+const CONTROL_BAR_ACTIONS: [(&[Action], &str, &str); 16] = [
+    (
+        &[Action::SelectDown, Action::SelectUp],
+        "Navigate",
+        "Navigate between entries",
+    ),
+    (&[Action::Select], "Select", "Select entry"),
+    (&[Action::Clear], "Clear", "Clear input"),
+    (&[Action::SwitchMatcher], "Matcher", "Switch matcher"),
+    (&[Action::EnterNormalMode], "Normal", "Normal mode"),
+    (&[Action::EnterInsertMode], "Search", "Search"),
+    (&[Action::TogglePicker], "Sessions", "Manage sessions"),
+    (&[Action::ToggleMark], "Mark", "Toggle mark"),
+    (
+        &[Action::OpenMarked(BatchTarget::BackgroundSessions)],
+        "Batch",
+        "Create marked sessions",
+    ),
+    (
+        &[Action::OpenMarked(BatchTarget::Tabs)],
+        "Tabs",
+        "Open marked as tabs",
+    ),
+    (&[Action::Open(OpenTarget::Tab)], "Tab", "Open in new tab"),
+    (
+        &[Action::Open(OpenTarget::FloatingPane)],
+        "Float",
+        "Open in floating pane",
+    ),
+    (
+        &[Action::Open(OpenTarget::TiledPane)],
+        "Pane",
+        "Open in tiled pane",
+    ),
+    (
+        &[Action::Open(OpenTarget::ExistingSession)],
+        "Switch",
+        "Switch to existing session",
+    ),
+    (
+        &[Action::Open(OpenTarget::FreshSession)],
+        "Fresh",
+        "Create fresh session",
+    ),
+    (&[Action::CopyPath], "Copy", "Copy path"),
+];
+
+/// The actions displayed in the control bar of [Picker::Sessions], like [CONTROL_BAR_ACTIONS].
+const SESSION_CONTROL_BAR_ACTIONS: [(&[Action], &str, &str); 10] = [
+    (
+        &[Action::SelectDown, Action::SelectUp],
+        "Navigate",
+        "Navigate between sessions",
+    ),
+    (&[Action::Select], "Switch", "Switch or resurrect"),
+    (&[Action::Clear], "Clear", "Clear input"),
+    (
+        &[Action::TogglePicker],
+        "Directories",
+        "Back to directories",
+    ),
+    (&[Action::KillSession], "Kill", "Kill session"),
+    (&[Action::RenameSession], "Rename", "Rename session"),
+    (&[Action::DetachSession], "Detach", "Detach"),
+    (&[Action::SwitchMatcher], "Matcher", "Switch matcher"),
+    (&[Action::EnterNormalMode], "Normal", "Normal mode"),
+    (&[Action::EnterInsertMode], "Search", "Search"),
+];
+
+impl ControlBar {
+    /// Generates the control bar of `picker` in `mode` from the first key bound to each of
+    /// [CONTROL_BAR_ACTIONS] or [SESSION_CONTROL_BAR_ACTIONS], so that it always reflects the
+    /// active keymap.
+    pub(crate) fn new(keymap: &Keymap, picker: Picker, mode: InputMode) -> Self {
+        let actions = match picker {
+            Picker::Paths => CONTROL_BAR_ACTIONS.as_slice(),
+            Picker::Sessions => SESSION_CONTROL_BAR_ACTIONS.as_slice(),
+        };
+        let segments = actions
+            .iter()
+            .filter_map(|(actions, short_label, full_label)| {
+                let keys = actions
+                    .iter()
+                    .filter_map(|action| keymap.keys(mode, *action).next())
+                    .map(display_key_sequence)
+                    .collect::<Vec<_>>();
+                // Single-character keys are juxtaposed, e.g. `↓↑`.
+                let separator = match keys.iter().all(|key| key.chars().count() == 1) {
+                    true => "",
+                    false => "/",
+                };
+                (!keys.is_empty()).then(|| ControlSegment {
+                    control: keys.join(separator),
+                    short_label,
+                    full_label,
+                })
+            })
+            .collect();
+
+        Self { segments }
+    }
+}
 
 impl<'ui> Frame<'ui> {
     fn fmt_pane_too_small(&self, f: &mut Formatter<'_>) -> Result {
         self.styles.fmt_pane_too_small(f)
     }
 
+    /// Prints the user input, or the pending session prompt in its place.
     fn fmt_user_input(&self, f: &mut Formatter<'_>) -> Result {
-        self.styles
-            .fmt_user_input(f, &SEARCH_PREFIX, self.context.user_input())
+        match self.context.session_prompt() {
+            Some(SessionPrompt::ConfirmKill(session_name)) => self
+                .styles
+                .fmt_confirmation(f, &format!("Kill session {session_name:?}?")),
+            Some(SessionPrompt::ConfirmMigration {
+                legacy_name,
+                session_name,
+                ..
+            }) => self.styles.fmt_confirmation(
+                f,
+                &format!("Rename session {legacy_name:?} to {session_name:?}?"),
+            ),
+            Some(SessionPrompt::Rename {
+                session_name,
+                new_name,
+            }) => self.styles.fmt_user_input(
+                f,
+                &format!("Rename {session_name:?} to"),
+                None,
+                new_name.as_str(),
+                new_name.cursor(),
+            ),
+            None => self.styles.fmt_user_input(
+                f,
+                match self.context.picker() {
+                    Picker::Paths => SEARCH_PREFIX,
+                    Picker::Sessions => SESSIONS_SEARCH_PREFIX,
+                },
+                self.context.input_mode(),
+                self.context.user_input(),
+                self.context.user_input_cursor(),
+            ),
+        }
     }
 
     fn fmt_user_input_divider(&self, f: &mut Formatter<'_>) -> Result {
-        self.styles.fmt_user_input_divider(
-            f,
-            self.context.match_count(),
-            self.context.choice_count(),
-            self.cols,
-        )
+        self.styles
+            .fmt_user_input_divider(f, self.context, &self.viewport, self.cols)
     }
 
     fn fmt_matched_results(&self, f: &mut Formatter<'_>) -> Result {
-        self.styles.fmt_matched_results(
-            f,
-            self.context.matches(),
-            self.context.selected_index(),
-            self.rows.saturating_sub(CHROME_LINE_COUNT),
-            self.cols,
-        )
+        match self.context.picker() {
+            Picker::Paths => self.styles.fmt_matched_results(
+                f,
+                self.context.matches(),
+                |entry| self.path_annotations(entry),
+                self.context.selected_index(),
+                &self.viewport,
+                self.cols,
+            ),
+            Picker::Sessions => self.styles.fmt_matched_results(
+                f,
+                self.context.session_matches(),
+                session_annotations,
+                self.context.selected_index(),
+                &self.viewport,
+                self.cols,
+            ),
+        }
+    }
+
+    fn path_annotations(&self, entry: &PathEntry) -> Annotations {
+        Annotations {
+            badge: self
+                .context
+                .session_badge(entry)
+                .map(|badge| badge_label(badge).to_string()),
+            // Keep entries aligned when some of them have no known kind.
+            icon: Some(entry.marker().map_or(" ", marker_icon)),
+            is_marked: self.context.is_marked(entry),
+        }
     }
 
     fn fmt_spacer(&self, f: &mut Formatter<'_>) -> Result {
         for _ in 0..self
             .rows
-            .saturating_sub(self.context.match_count() + CHROME_LINE_COUNT)
+            .saturating_sub(self.viewport.visible_count() + CHROME_LINE_COUNT)
         {
             writeln!(f)?;
         }
@@ -76,7 +224,7 @@ impl<'ui> Frame<'ui> {
     }
 
     fn fmt_control_bar(&self, f: &mut Formatter<'_>) -> Result {
-        self.styles.fmt_control_bar(f, &CONTROL_BAR, self.cols)
+        self.styles.fmt_control_bar(f, self.control_bar, self.cols)
     }
 
     /// Prints errors, if any.
@@ -86,6 +234,70 @@ impl<'ui> Frame<'ui> {
     }
 }
 
+fn badge_label(badge: SessionBadge) -> &'static str {
+    match badge {
+        SessionBadge::Current => "current",
+        SessionBadge::Active => "active",
+        SessionBadge::Resurrectable => "exited",
+    }
+}
+
+fn marker_icon(kind: MarkerKind) -> &'static str {
+    match kind {
+        MarkerKind::Git | MarkerKind::GitBare | MarkerKind::GitWorktree => "±",
+        MarkerKind::Jujutsu => "◇",
+        MarkerKind::Mercurial => "☿",
+        MarkerKind::Sapling => "♠",
+        MarkerKind::Pijul => "◎",
+        MarkerKind::Project => "∗",
+    }
+}
+
+/// Details about a session, e.g. `current, 3 tabs, 1 client, src/nix-config`.
+fn session_annotations(session: &SessionEntry) -> Annotations {
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("1 {noun}"),
+        count => format!("{count} {noun}s"),
+    };
+    let status = match (session.status, session.exited_for) {
+        (SessionStatus::Current, _) => Some("current".to_string()),
+        (SessionStatus::Running, _) => None,
+        (SessionStatus::Exited, Some(exited_for)) => {
+            Some(format!("exited {} ago", format_duration(exited_for)))
+        }
+        (SessionStatus::Exited, None) => Some("exited".to_string()),
+    };
+    let counts = match session.status {
+        SessionStatus::Exited => vec![],
+        _ => vec![
+            plural(session.tab_count, "tab"),
+            plural(session.connected_clients, "client"),
+        ],
+    };
+    let badge = status
+        .into_iter()
+        .chain(counts)
+        .chain(session.path.iter().map(|path| path.display().to_string()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Annotations {
+        badge: Some(badge),
+        icon: None,
+        is_marked: false,
+    }
+}
+
+/// Formats `duration` with its largest unit only, e.g. `3h`.
+fn format_duration(duration: Duration) -> String {
+    match duration.as_secs() {
+        secs if secs < 60 => format!("{secs}s"),
+        secs if secs < 60 * 60 => format!("{}m", secs / 60),
+        secs if secs < 24 * 60 * 60 => format!("{}h", secs / (60 * 60)),
+        secs => format!("{}d", secs / (24 * 60 * 60)),
+    }
+}
+
 impl Display for Frame<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         // Bail immediately if we don't have the space to render the bare minimum UI, which
@@ -112,3 +324,113 @@ impl Display for Frame<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{parse_keys, KeymapPreset};
+
+    fn controls(keymap: &Keymap, picker: Picker, mode: InputMode) -> Vec<String> {
+        ControlBar::new(keymap, picker, mode)
+            .segments
+            .into_iter()
+            .map(|segment| segment.control)
+            .collect()
+    }
+
+    #[test]
+    fn control_bar_follows_keymap() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            controls(&keymap, Picker::Paths, InputMode::Insert),
+            [
+                "↓↑",
+                "ENTER",
+                "ESC",
+                "Ctrl t",
+                "Ctrl l",
+                "TAB",
+                "Alt ENTER",
+                "Alt o",
+                "Alt t",
+                "Alt w",
+                "Alt p",
+                "Alt s",
+                "Alt n",
+                "Alt y"
+            ]
+        );
+
+        keymap.bind(
+            InputMode::Insert,
+            Action::SelectDown,
+            parse_keys("Ctrl n").unwrap(),
+        );
+        keymap.bind(InputMode::Insert, Action::SwitchMatcher, vec![]);
+        keymap.bind(InputMode::Insert, Action::ToggleMark, vec![]);
+        assert_eq!(
+            controls(&keymap, Picker::Paths, InputMode::Insert)[..5],
+            ["Ctrl n/↑", "ENTER", "ESC", "Ctrl l", "Alt ENTER"]
+        );
+    }
+
+    #[test]
+    fn control_bar_follows_input_mode() {
+        let keymap = Keymap::new(KeymapPreset::Vim);
+        assert_eq!(
+            controls(&keymap, Picker::Paths, InputMode::Insert)[..5],
+            ["↓↑", "ENTER", "Ctrl t", "ESC", "Ctrl l"]
+        );
+        assert_eq!(
+            controls(&keymap, Picker::Paths, InputMode::Normal),
+            ["jk", "ENTER", "Ctrl t", "/", "Ctrl l", "m", "b", "o", "t", "w", "p", "s", "n", "y"]
+        );
+    }
+
+    #[test]
+    fn session_control_bar() {
+        let keymap = Keymap::new(KeymapPreset::Vim);
+        assert_eq!(
+            controls(&keymap, Picker::Sessions, InputMode::Insert),
+            ["↓↑", "ENTER", "Ctrl l", "Ctrl x", "Ctrl r", "Ctrl d", "Ctrl t", "ESC"]
+        );
+        assert_eq!(
+            controls(&keymap, Picker::Sessions, InputMode::Normal),
+            ["jk", "ENTER", "Ctrl l", "x", "r", "D", "Ctrl t", "/"]
+        );
+    }
+
+    #[test]
+    fn session_details() {
+        let session = |status, exited_for, path: Option<&str>| SessionEntry {
+            name: "nix-config".to_string(),
+            status,
+            tab_count: 1,
+            connected_clients: 2,
+            exited_for,
+            path: path.map(Into::into),
+        };
+        let badge = |session| session_annotations(&session).badge.unwrap();
+
+        assert_eq!(
+            badge(session(
+                SessionStatus::Current,
+                None,
+                Some("src/nix-config")
+            )),
+            "current, 1 tab, 2 clients, src/nix-config"
+        );
+        assert_eq!(
+            badge(session(SessionStatus::Running, None, None)),
+            "1 tab, 2 clients"
+        );
+        assert_eq!(
+            badge(session(
+                SessionStatus::Exited,
+                Some(Duration::from_secs(3 * 60 * 60 + 59)),
+                None
+            )),
+            "exited 3h ago"
+        );
+    }
+}
@@ -1,16 +1,55 @@
-use crate::fuzzy_search_context::FuzzySearchContext;
+use crate::fuzzy_search_context::{FuzzySearchContext, Picker};
 
-use styles::Styles;
+use crate::keymap::{InputMode, Keymap};
+use std::collections::BTreeMap;
+
+use styles::{ControlBar, Styles};
 
 mod frame;
 mod renderer;
 mod styles;
+mod viewport;
 
 pub const PANE_TITLE: &'static str = "Select a directory:";
 
-#[derive(Default)]
 pub(crate) struct Renderer {
     styles: Styles,
+    /// Generated from the active keymap, for each picker and input mode.
+    control_bars: BTreeMap<(Picker, InputMode), ControlBar>,
+    /// Persisted across frames so that the list only scrolls when the selection would otherwise
+    /// leave the screen.
+    viewport: Viewport,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            styles: Styles::default(),
+            control_bars: control_bars(&Keymap::default()),
+            viewport: Viewport::default(),
+        }
+    }
+}
+
+fn control_bars(keymap: &Keymap) -> BTreeMap<(Picker, InputMode), ControlBar> {
+    [Picker::Paths, Picker::Sessions]
+        .into_iter()
+        .flat_map(|picker| {
+            [InputMode::Insert, InputMode::Normal]
+                .map(|mode| ((picker, mode), ControlBar::new(keymap, picker, mode)))
+        })
+        .collect()
+}
+
+/// The window of results visible on screen.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct Viewport {
+    /// The index of the first visible result.
+    offset: usize,
+    /// The number of lines available to display results.
+    rows: usize,
+    /// The total number of results.
+    count: usize,
 }
 
 /// Represents a plugin UI frame of size [rows]×[cols].
@@ -21,4 +60,6 @@ pub(crate) struct Frame<'ui> {
     cols: usize,
     context: &'ui FuzzySearchContext,
     styles: &'ui Styles,
+    control_bar: &'ui ControlBar,
+    viewport: Viewport,
 }
@@ -1,18 +1,41 @@
-use super::{Frame, Renderer};
+use super::{frame::CHROME_LINE_COUNT, Frame, Renderer};
 use crate::fuzzy_search_context::FuzzySearchContext;
+use crate::keymap::Keymap;
+
+use super::control_bars;
 
 impl Renderer {
+    /// Regenerates the control bars from `keymap`.
+    pub fn set_keymap(&mut self, keymap: &Keymap) {
+        self.control_bars = control_bars(keymap);
+    }
+
     pub fn next_frame<'ui>(
-        &'ui self,
+        &'ui mut self,
         rows: usize,
         cols: usize,
         context: &'ui FuzzySearchContext,
     ) -> Frame<'ui> {
+        self.viewport.scroll_to(
+            context.selected_index(),
+            context.match_count(),
+            rows.saturating_sub(CHROME_LINE_COUNT),
+        );
+
         Frame {
             rows,
             cols,
             context,
             styles: &self.styles,
+            control_bar: &self.control_bars
+                [&(context.picker(), context.input_mode().unwrap_or_default())],
+            viewport: self.viewport,
         }
     }
+
+    /// The number of results visible on screen as of the last frame, used to move the selection
+    /// by a full page.
+    pub fn page_size(&self) -> usize {
+        self.viewport.rows.max(1)
+    }
 }
@@ -4,11 +4,14 @@
 #![allow(unstable_name_collisions)]
 
 use crate::{
-    fuzzy_search_context::FuzzySearchContext,
+    fuzzy_search_context::{FuzzySearchContext, Picker},
+    keymap::InputMode,
     matcher::{Choice, Match},
 };
 use std::fmt::{Formatter, Result};
 
+use super::Viewport;
+
 use ansi_term::{
     ANSIString, ANSIStrings,
     Colour::{self, Fixed, RGB},
@@ -29,23 +32,35 @@ pub(crate) struct Styles {
     selected_and_matched: Style,
     ellipsized: Style,
     selected_and_ellipsized: Style,
+    badge: Style,
+    selected_and_badge: Style,
+    marker: Style,
 
     control_background: Style,
     control_keycode: Style,
     control_label: Style,
 }
 
-pub(crate) struct ControlSegment<'cs> {
-    pub(crate) control: &'cs str,
-    pub(crate) short_label: &'cs str,
-    pub(crate) full_label: &'cs str,
+/// What to display next to an entry, besides its matched characters.
+pub(crate) struct Annotations {
+    /// Details about the entry, displayed after it between brackets.
+    pub(crate) badge: Option<String>,
+    /// A single-column symbol displayed before the entry, e.g. the VCS of a directory.
+    pub(crate) icon: Option<&'static str>,
+    pub(crate) is_marked: bool,
+}
+
+pub(crate) struct ControlSegment {
+    pub(crate) control: String,
+    pub(crate) short_label: &'static str,
+    pub(crate) full_label: &'static str,
 }
 
-pub(crate) struct ControlBar<'cb> {
-    pub(crate) segments: [ControlSegment<'cb>; 3],
+pub(crate) struct ControlBar {
+    pub(crate) segments: Vec<ControlSegment>,
 }
 
-impl ControlBar<'_> {
+impl ControlBar {
     const SEGMENT_SEPARATOR: &'static str = " / ";
 
     fn full_label_len(&self) -> usize {
@@ -56,9 +71,11 @@ impl ControlBar<'_> {
             .sum()
     }
 
-    fn short_label_len(&self) -> usize {
+    /// The length of the first `count` segments with their short label.
+    fn short_label_len(&self, count: usize) -> usize {
         self.segments
             .iter()
+            .take(count)
             .map(|segment| {
                 segment.control.chars().count() + segment.short_label.chars().count() + 3
             })
@@ -80,7 +97,7 @@ impl ControlBar<'_> {
             .map(|segment| {
                 vec![
                     styles.control_background.paint("<"),
-                    styles.control_keycode.paint(segment.control),
+                    styles.control_keycode.paint(segment.control.as_str()),
                     styles.control_background.paint("> "),
                     styles.control_label.paint(segment.full_label),
                 ]
@@ -90,15 +107,16 @@ impl ControlBar<'_> {
             .collect()
     }
 
-    fn render_short<'s>(&'s self, styles: &'s Styles) -> Vec<ANSIString<'s>> {
+    fn render_short<'s>(&'s self, styles: &'s Styles, count: usize) -> Vec<ANSIString<'s>> {
         let segment_separators = vec![ControlBar::render_separator(styles)];
 
         self.segments
             .iter()
+            .take(count)
             .map(|segment| {
                 vec![
                     styles.control_background.paint("<"),
-                    styles.control_keycode.paint(segment.control),
+                    styles.control_keycode.paint(segment.control.as_str()),
                     styles.control_background.paint("> "),
                     styles.control_label.paint(segment.short_label),
                 ]
@@ -109,11 +127,15 @@ impl ControlBar<'_> {
     }
 
     fn render<'s>(&'s self, styles: &'s Styles, cols: usize) -> Option<Vec<ANSIString<'s>>> {
-        match cols {
-            _ if self.full_label_len() <= cols => Some(self.render_full(styles)),
-            _ if self.short_label_len() <= cols => Some(self.render_short(styles)),
-            _ => None,
+        if self.full_label_len() <= cols {
+            return Some(self.render_full(styles));
         }
+
+        // Drop trailing segments, which are the least important ones, until the bar fits.
+        (1..=self.segments.len())
+            .rev()
+            .find(|&count| self.short_label_len(count) <= cols)
+            .map(|count| self.render_short(styles, count))
     }
 }
 
@@ -156,6 +178,12 @@ impl Default for Styles {
                 .dimmed()
                 .fg(CATPPUCCIN_ON_SURFACE_BLUE)
                 .on(CATPPUCCIN_SURFACE_BLUE),
+            badge: Style::new().fg(Fixed(CYAN)).italic(),
+            selected_and_badge: Style::new()
+                .fg(Fixed(CYAN))
+                .on(CATPPUCCIN_SURFACE_BLUE)
+                .italic(),
+            marker: Style::new().fg(Fixed(GREEN)).bold(),
 
             control_background: Style::new().on(CATPPUCCIN_MANTLE),
             control_keycode: Style::new().fg(Fixed(GREEN)).on(CATPPUCCIN_MANTLE).bold(),
@@ -166,6 +194,9 @@ impl Default for Styles {
 
 const RESET: &str = "\x1B[0m";
 
+/// Frames of the activity indicator displayed while scans are pending.
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 impl Styles {
     pub(crate) fn fmt_pane_too_small(&self, f: &mut Formatter<'_>) -> Result {
         writeln!(f, "{}", self.error.paint("Plugin pane is too small"))
@@ -175,31 +206,103 @@ impl Styles {
         &self,
         f: &mut Formatter<'_>,
         prompt: &str,
+        mode: Option<InputMode>,
         input: &str,
+        cursor: usize,
     ) -> Result {
+        // Modal keymaps display their current mode before the prompt.
+        if let Some(mode) = mode {
+            write!(f, "{} ", self.prompt.paint(mode.name()))?;
+        }
+
+        // Highlight the character under the cursor, or a trailing space if the cursor is at the end
+        // of the input. `cursor` is a byte offset on a character boundary.
+        let (before, after) = input.split_at(cursor);
+        let mut after = after.chars();
+        let under_cursor = after.next().unwrap_or(' ');
+        let mut ch_buf = [0u8; 4];
         writeln!(
             f,
-            "{} {}{}",
+            "{} {before}{}{}",
             self.prompt.paint(prompt),
-            input,
-            self.cursor.paint(" ")
+            self.cursor
+                .paint(under_cursor.encode_utf8(&mut ch_buf) as &str),
+            after.as_str()
+        )
+    }
+
+    /// Asks the user to confirm an action with `y`, in place of the user input.
+    pub(crate) fn fmt_confirmation(&self, f: &mut Formatter<'_>, question: &str) -> Result {
+        writeln!(
+            f,
+            "{} {}",
+            self.warning.paint(question),
+            self.prompt.paint("(y/n)")
         )
     }
 
     pub(crate) fn fmt_user_input_divider(
         &self,
         f: &mut Formatter<'_>,
-        matched: usize,
-        total: usize,
+        context: &FuzzySearchContext,
+        viewport: &Viewport,
         cols: usize,
     ) -> Result {
-        let stats = format!("  {}/{}", matched, total);
-        let fills = "─".repeat(cols.saturating_sub(stats.len() + 3));
+        let marked = match (context.picker(), context.marked_count()) {
+            (Picker::Sessions, _) | (_, 0) => String::new(),
+            (Picker::Paths, count) => format!(" ● {count} marked"),
+        };
+        let stats = format!(
+            "  {}/{} ({}){marked}",
+            context.match_count(),
+            context.choice_count(),
+            context.matcher_kind().name()
+        );
+        let progress = context.scan_progress();
+        let scroll = match (viewport.has_more_above(), viewport.has_more_below()) {
+            (false, false) => String::new(),
+            (above, below) => format!(
+                " {} {}-{}",
+                match (above, below) {
+                    (true, true) => "↕",
+                    (true, false) => "↑",
+                    _ => "↓",
+                },
+                viewport.offset() + 1,
+                viewport.offset() + viewport.visible_count(),
+            ),
+        };
+        let scanning = match progress.pending {
+            0 => String::new(),
+            1 => format!(
+                " {} scanning 1 source",
+                SPINNER_FRAMES[progress.tick % SPINNER_FRAMES.len()]
+            ),
+            pending => format!(
+                " {} scanning {pending} sources",
+                SPINNER_FRAMES[progress.tick % SPINNER_FRAMES.len()]
+            ),
+        };
+        let failed = progress
+            .failed
+            .iter()
+            .map(|label| format!(" ✗ {label}"))
+            .collect::<String>();
+        let fills = "─".repeat(cols.saturating_sub(
+            stats.chars().count()
+                + scroll.chars().count()
+                + scanning.chars().count()
+                + failed.chars().count()
+                + 3,
+        ));
 
         writeln!(
             f,
-            "{} {}",
+            "{}{}{}{} {}",
             self.separator.paint(stats),
+            self.separator.paint(scroll),
+            self.warning.paint(scanning),
+            self.error.paint(failed),
             self.separator.paint(fills)
         )
     }
@@ -208,13 +311,28 @@ impl Styles {
         &self,
         f: &mut Formatter<'_>,
         matched_results: impl Iterator<Item = &'m Match<C>>,
+        annotations: impl Fn(&C) -> Annotations,
         selected_index: usize,
-        rows: usize,
+        viewport: &Viewport,
         cols: usize,
     ) -> Result {
         let mut ch_buf = [0u8; 4];
-        for (index, m) in matched_results.take(rows).enumerate() {
-            self.fmt_matched_line(f, &mut ch_buf, m, index == selected_index, cols)?;
+        for (index, m) in matched_results
+            .enumerate()
+            .skip(viewport.offset())
+            .take(viewport.visible_count())
+        {
+            let Some(annotations) = m.choice.upgrade().map(|choice| annotations(&choice)) else {
+                continue;
+            };
+            self.fmt_matched_line(
+                f,
+                &mut ch_buf,
+                m,
+                annotations,
+                index == selected_index,
+                cols,
+            )?;
         }
 
         Ok(())
@@ -225,22 +343,34 @@ impl Styles {
         f: &mut Formatter<'_>,
         ch_buf: &mut [u8; 4],
         m: &Match<C>,
+        annotations: Annotations,
         is_selected: bool,
         cols: usize,
     ) -> Result {
-        let cols = cols.saturating_sub(2); // Take into account prefix.
+        let cols = cols.saturating_sub(3); // Take into account prefix (caret and marker columns).
 
         if cols < 3 {
             unreachable!("rendering function not adequate for narrow screens");
         }
 
+        // Only display the badge if it leaves enough room for the entry.
+        let badge = annotations
+            .badge
+            .map(|badge| format!(" [{badge}]"))
+            .filter(|badge| cols >= badge.chars().count() + 3);
+        let cols = cols.saturating_sub(badge.as_ref().map_or(0, |b| b.chars().count()));
+        // Only display the icon if it leaves enough room for the entry.
+        let icon = annotations.icon.filter(|_| cols >= 5);
+        let cols = cols.saturating_sub(icon.map_or(0, |_| 2));
+
         let Some(choice) = m.choice.upgrade() else {
             // TODO: we should not have `Weak<T>` at this point.
             // NOTE: this would mess up the vertical rendering.
             return Ok(());
         };
         let choice = choice.repr();
-        let (entry, offset) = if choice.len() > cols {
+        let choice_len = choice.chars().count();
+        let (entry, offset) = if choice_len > cols {
             let ridx = cols.saturating_sub(1);
             (
                 // m.entry = "abcdef"
@@ -251,15 +381,17 @@ impl Styles {
                 //             2345
                 slice_from_end(choice, ridx.saturating_sub(1))
                     .expect("entry contains at least `cols - 1` characters"),
-                choice.len().saturating_sub(cols - 1),
+                choice_len.saturating_sub(cols - 1),
             )
         } else {
             (choice, 0)
         };
         // m.entry = "abcdef"
         // entry   = "cdef"
+        // Match indices are character indices, not byte indices.
         let styled_entry = entry
-            .char_indices()
+            .chars()
+            .enumerate()
             // (0, entry[0]), (1, entry[1]), (2, entry[2]), …
             .map(|(idx, ch)| (idx + offset, ch)) // Reframe indices.
             // (offset, entry[0]), (offset + 1, entry[1]), (offset + 2, entry[2]), …
@@ -287,27 +419,54 @@ impl Styles {
         } else {
             styled_entry
         };
+        let styled_entry = match badge {
+            Some(badge) => format!(
+                "{styled_entry}{}",
+                if is_selected {
+                    self.selected_and_badge
+                } else {
+                    self.badge
+                }
+                .paint(badge)
+            ),
+            None => styled_entry,
+        };
+        let styled_entry = match icon {
+            Some(icon) => format!(
+                "{} {styled_entry}",
+                if is_selected {
+                    self.selected_and_badge
+                } else {
+                    self.badge
+                }
+                .paint(icon)
+            ),
+            None => styled_entry,
+        };
+        let marker = self
+            .marker
+            .paint(if annotations.is_marked { "●" } else { " " });
         if is_selected {
-            self.fmt_selected_line(f, &styled_entry)?;
+            self.fmt_selected_line(f, &marker.to_string(), &styled_entry)?;
         } else {
-            writeln!(f, "  {styled_entry}")?;
+            writeln!(f, " {marker} {styled_entry}")?;
         }
 
         Ok(())
     }
 
-    fn fmt_selected_line(&self, f: &mut Formatter<'_>, entry: &str) -> Result {
+    fn fmt_selected_line(&self, f: &mut Formatter<'_>, marker: &str, entry: &str) -> Result {
         // TODO: Reuse [CATPPUCCIN_SURFACE_BLUE] instead of duplicating the value.
         writeln!(
             f,
-            "{}{entry}\u{1b}[48;2;32;49;71m\u{1b}[0K",
-            self.caret.paint("▌ "),
+            "{}{marker} {entry}\u{1b}[48;2;32;49;71m\u{1b}[0K",
+            self.caret.paint("▌"),
         )?;
         write!(f, "{}", RESET)
     }
 
-    /// Realistically, the control bar is fully static, so it could be hard-coded as a single
-    /// &'static str, but then dealing with horizontal available space and truncation might become
+    /// The control bar only changes with the keymap, so it could be rendered once as a single
+    /// string, but then dealing with horizontal available space and truncation might become
     /// nerve-racking.
     pub(crate) fn fmt_control_bar(
         &self,
@@ -0,0 +1,85 @@
+use super::Viewport;
+
+impl Viewport {
+    /// Scrolls the viewport by the minimum amount necessary for `selected_index` to be visible,
+    /// given `rows` lines available to display `count` results.
+    pub(super) fn scroll_to(&mut self, selected_index: usize, count: usize, rows: usize) {
+        self.rows = rows;
+        self.count = count;
+
+        if selected_index < self.offset {
+            self.offset = selected_index;
+        } else if selected_index >= self.offset + rows {
+            self.offset = selected_index + 1 - rows;
+        }
+
+        // Do not leave blank lines at the bottom if results could fill them (e.g. after the list of
+        // results shrank).
+        self.offset = self.offset.min(count.saturating_sub(rows));
+    }
+
+    /// The index of the first visible result.
+    pub(super) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of visible results.
+    pub(super) fn visible_count(&self) -> usize {
+        self.count.saturating_sub(self.offset).min(self.rows)
+    }
+
+    /// Whether some results are hidden above the viewport.
+    pub(super) fn has_more_above(&self) -> bool {
+        self.offset > 0
+    }
+
+    /// Whether some results are hidden below the viewport.
+    pub(super) fn has_more_below(&self) -> bool {
+        self.offset + self.rows < self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_to_keeps_selection_visible() {
+        let mut viewport = Viewport::default();
+
+        viewport.scroll_to(0, 10, 3);
+        assert_eq!(viewport.offset(), 0);
+
+        viewport.scroll_to(2, 10, 3);
+        assert_eq!(viewport.offset(), 0);
+
+        viewport.scroll_to(3, 10, 3);
+        assert_eq!(viewport.offset(), 1);
+
+        viewport.scroll_to(9, 10, 3);
+        assert_eq!(viewport.offset(), 7);
+        assert!(viewport.has_more_above());
+        assert!(!viewport.has_more_below());
+
+        viewport.scroll_to(8, 10, 3);
+        assert_eq!(viewport.offset(), 7);
+
+        viewport.scroll_to(5, 10, 3);
+        assert_eq!(viewport.offset(), 5);
+        assert_eq!(viewport.visible_count(), 3);
+    }
+
+    #[test]
+    fn scroll_to_fills_viewport_when_results_shrink() {
+        let mut viewport = Viewport::default();
+
+        viewport.scroll_to(9, 10, 3);
+        assert_eq!(viewport.offset(), 7);
+
+        viewport.scroll_to(1, 2, 3);
+        assert_eq!(viewport.offset(), 0);
+        assert_eq!(viewport.visible_count(), 2);
+        assert!(!viewport.has_more_above());
+        assert!(!viewport.has_more_below());
+    }
+}
@@ -1,23 +1,43 @@
 use crate::{
     marshall_plugin::{deserialize, serialize},
-    workers::protocol::{FileSystemWorkerMessage, RepositoryCrawlerResponse},
+    workers::protocol::{
+        FileSystemWorkerMessage, RepositoryCrawlerCompletion, RepositoryCrawlerFailure,
+        RepositoryCrawlerResponse,
+    },
 };
 
-use super::fs::list_repositories;
+use super::fs::{list_repositories, Discovery};
 use super::protocol::RepositoryCrawlerRequest;
 
 use anyhow;
+use project_markers::MarkerKind;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 use zellij_tile::prelude::*;
 
 type Result = anyhow::Result<()>;
 
+/// The maximum number of repositories sent to the plugin in a single batch.
+const BATCH_SIZE: usize = 64;
+
+/// The maximum amount of time a discovered repository is held back before being sent to the
+/// plugin, give or take the time to inspect a directory, so that slow scans still yield visible
+/// progress.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Default, Deserialize, Serialize)]
 pub(crate) struct FileSystemWorker {}
 
 impl FileSystemWorker {
-    fn parse_request(&mut self, message: String, payload: String) -> Result {
+    fn parse_request(
+        &mut self,
+        message: String,
+        payload: String,
+    ) -> anyhow::Result<RepositoryCrawlerRequest> {
         let message = deserialize::<FileSystemWorkerMessage>(&message)
             .with_context(|| "deserializing inbound message from plugin")?;
         assert!(
@@ -25,18 +45,110 @@ impl FileSystemWorker {
             "unsupported worker message"
         );
 
-        let request = deserialize::<RepositoryCrawlerRequest>(&payload)
-            .with_context(|| "deserializing inbound payload from plugin")?;
+        deserialize::<RepositoryCrawlerRequest>(&payload)
+            .with_context(|| "deserializing inbound payload from plugin")
+    }
+
+    /// Crawls `root` and streams the results back to the plugin in batches, followed by a final
+    /// `FileSystemWorkerMessage::CrawlComplete` message. Failures are left to the caller to report.
+    fn crawl(&mut self, request: RepositoryCrawlerRequest) -> Result {
+        let RepositoryCrawlerRequest {
+            root,
+            max_depth,
+            markers,
+            excludes,
+            descend,
+            follow_symlinks,
+            host_cwd,
+        } = request;
+        let mut batch = BTreeMap::new();
+        let mut labelled_batch = BTreeMap::new();
+        let mut last_flush = Instant::now();
+        let mut repository_count = 0;
+
+        // The plugin's CWD is mounted under `/host`.
+        let discoveries = list_repositories(
+            &PathBuf::from("/host").join(&root),
+            max_depth,
+            markers,
+            &excludes,
+            descend,
+            follow_symlinks,
+            host_cwd,
+        )
+        .with_context(|| format!("reading {root:?}"))?
+        .with_tick_interval(BATCH_INTERVAL);
+        for discovery in discoveries {
+            match discovery {
+                Discovery::Repository(repository, marker) => {
+                    batch.insert(repository, marker);
+                    repository_count += 1;
+                }
+                Discovery::Labelled {
+                    label,
+                    path,
+                    marker,
+                } => {
+                    labelled_batch.insert(label, (path, marker));
+                    repository_count += 1;
+                }
+                // Gives a chance to send a partial batch while no repository is found.
+                Discovery::Tick => (),
+            }
 
-        self.crawl(&request.root, request.max_depth)
+            let batch_len = batch.len() + labelled_batch.len();
+            if batch_len >= BATCH_SIZE || (batch_len > 0 && last_flush.elapsed() >= BATCH_INTERVAL)
+            {
+                self.post_batch(
+                    &root,
+                    std::mem::take(&mut batch),
+                    std::mem::take(&mut labelled_batch),
+                )?;
+                last_flush = Instant::now();
+            }
+        }
+
+        if !batch.is_empty() || !labelled_batch.is_empty() {
+            self.post_batch(&root, batch, labelled_batch)?;
+        }
+
+        post_message_to_plugin(PluginMessage::new_to_plugin(
+            &serialize(&FileSystemWorkerMessage::CrawlComplete)?,
+            &serialize(&RepositoryCrawlerCompletion {
+                root,
+                repository_count,
+            })?,
+        ));
+
+        Ok(())
     }
 
-    fn crawl(&mut self, root: &PathBuf, max_depth: usize) -> Result {
-        let repositories = list_repositories(root, max_depth);
+    fn post_batch(
+        &self,
+        root: &Path,
+        repositories: BTreeMap<PathBuf, MarkerKind>,
+        labelled: BTreeMap<PathBuf, (PathBuf, MarkerKind)>,
+    ) -> Result {
+        post_message_to_plugin(PluginMessage::new_to_plugin(
+            &serialize(&FileSystemWorkerMessage::CrawlBatch)?,
+            &serialize(&RepositoryCrawlerResponse {
+                root: root.to_path_buf(),
+                repositories,
+                labelled,
+            })?,
+        ));
+
+        Ok(())
+    }
 
+    /// Reports to the plugin that the crawl of `root` failed because of `error`.
+    fn post_failure(&self, root: PathBuf, error: &anyhow::Error) -> Result {
         post_message_to_plugin(PluginMessage::new_to_plugin(
-            &serialize(&FileSystemWorkerMessage::Crawl)?,
-            &serialize(&RepositoryCrawlerResponse { repositories })?,
+            &serialize(&FileSystemWorkerMessage::CrawlFailed)?,
+            &serialize(&RepositoryCrawlerFailure {
+                root,
+                reason: format!("{error:#}"),
+            })?,
         ));
 
         Ok(())
@@ -45,10 +157,22 @@ impl FileSystemWorker {
 
 impl<'de> ZellijWorker<'de> for FileSystemWorker {
     fn on_message(&mut self, message: String, payload: String) {
-        if let Err(error) = self.parse_request(message, payload) {
-            // NOTE: if we failed to serialize our response, chances are we're not going to be able
-            // to send the error back to the plugin. Fallback to logging the error.
+        let request = match self.parse_request(message, payload) {
+            Ok(request) => request,
+            Err(error) => {
+                // Without a request, there is no scan to report the error for.
+                eprintln!("failed to parse scan request: {error:?}");
+                return;
+            }
+        };
+        let root = request.root.clone();
+        if let Err(error) = self.crawl(request) {
             eprintln!("failed to scan host: {error:?}");
+            // NOTE: if we failed to serialize our response, chances are we're not going to be able
+            // to send the failure back to the plugin either. Fallback to logging the error.
+            if let Err(error) = self.post_failure(root, &error) {
+                eprintln!("failed to report scan failure: {error:?}");
+            }
         }
     }
 }
@@ -1,66 +1,330 @@
-use std::collections::BTreeSet;
+use project_markers::{
+    is_linked_worktree, linked_worktrees, real_path, Entry, ExcludeScope, ExcludeSet, MarkerKind,
+    MarkerSet, VisitedDirs,
+};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-// TODO: Change this to return an iterator instead so that early results are immediately visible.
-pub(crate) fn list_repositories(root: &PathBuf, max_depth: usize) -> BTreeSet<PathBuf> {
-    let mut repositories = BTreeSet::new();
+/// An entry of a directory.
+struct Child {
+    path: PathBuf,
+    /// Whether the entry is a directory, or a symbolic link to a directory when following them.
+    is_dir: bool,
+    is_symlink: bool,
+}
+
+/// A project found by [RepositoryIter].
+pub(crate) enum Discovery {
+    /// A project root, relative to the scanned root, along with the kind of marker that identified
+    /// it.
+    Repository(PathBuf, MarkerKind),
+    /// A project whose path differs from its label, relative to the scanned root: a linked worktree
+    /// of a Git repository, labelled `<repository>@<worktree>`, or a project reached through a
+    /// symbolic link, labelled with the path of the link. Its path is relative to the plugin's CWD
+    /// if it lives under it, and absolute on the host otherwise, see [host_path].
+    Labelled {
+        label: PathBuf,
+        path: PathBuf,
+        marker: MarkerKind,
+    },
+    /// Yielded while inspecting directories, at most once per tick interval, so that the caller
+    /// gets control back on slow filesystems. See [RepositoryIter::with_tick_interval].
+    Tick,
+}
+
+/// Lists all project roots under [root], relative to [root], along with the kind of marker that
+/// identified them. Stop traversing at [max_depth], and skip the directories matching [excludes].
+///
+/// If [descend] is set, keep traversing below project roots to find nested repositories, and list
+/// the linked worktrees of Git repositories, wherever they live. Worktrees found while traversing
+/// are left to their repository, unless it does not list them, e.g. because it lives outside of
+/// [root] or is excluded. They are therefore only yielded once all directories were inspected.
+///
+/// If [follow_symlinks] is set, traverse symbolic links to directories as well. Each directory is
+/// traversed once, however many links lead to it, which also breaks cycles. Links are traversed
+/// last, so that projects are labelled with their real path when reachable without links.
+///
+/// Repositories are yielded as they are discovered so that early results are immediately visible.
+/// Fails if [root] cannot be read. Other unreadable directories are skipped.
+pub(crate) fn list_repositories(
+    root: &PathBuf,
+    max_depth: usize,
+    markers: MarkerSet,
+    excludes: &ExcludeSet,
+    descend: bool,
+    follow_symlinks: bool,
+    host_cwd: PathBuf,
+) -> io::Result<RepositoryIter> {
     let mut dirs_to_walk = Vec::new();
+    let visited = follow_symlinks.then(VisitedDirs::default);
+    if let Some(visited) = &visited {
+        visited.insert(root);
+    }
 
-    let child_dirs = get_child_directories(root);
-    if !child_dirs.is_empty() {
-        dirs_to_walk.push((root.clone(), child_dirs, /* depth */ 1));
+    let children = get_children(root, follow_symlinks)?;
+    if !children.is_empty() {
+        dirs_to_walk.push(PendingDir {
+            path: root.clone(),
+            children,
+            scope: excludes.scope(root),
+            depth: 1,
+            via_symlink: false,
+        });
     }
 
-    'outer: while let Some((parent, children, depth)) = dirs_to_walk.pop() {
-        assert!(
-            children.iter().all(|e| fs::metadata(e)
-                .map(|e| e.file_type().is_dir())
-                .unwrap_or(false)),
-            "internal error: all children must be valid directories"
-        );
-
-        for dir in &children {
-            if dir
-                .file_name()
-                .map(|fname| fname == ".git")
-                .unwrap_or(false)
+    Ok(RepositoryIter {
+        root: root.clone(),
+        max_depth,
+        markers,
+        descend,
+        host_cwd,
+        visited,
+        dirs_to_walk,
+        links_to_walk: Vec::new(),
+        discoveries: Vec::new(),
+        crawled_worktrees: Vec::new(),
+        listed_worktrees: HashSet::new(),
+        tick_interval: None,
+        last_tick: Instant::now(),
+    })
+}
+
+/// Iterator over the repositories found under a root directory. See [list_repositories].
+pub(crate) struct RepositoryIter {
+    root: PathBuf,
+    max_depth: usize,
+    markers: MarkerSet,
+    descend: bool,
+    /// The plugin's CWD on the host.
+    host_cwd: PathBuf,
+    /// The directories traversed so far, if following symbolic links.
+    visited: Option<VisitedDirs>,
+    /// Stack of directories left to inspect.
+    dirs_to_walk: Vec<PendingDir>,
+    /// Stack of symbolic links to directories left to inspect once [dirs_to_walk] is exhausted.
+    links_to_walk: Vec<PendingDir>,
+    /// Projects found along with the last inspected directory, left to yield.
+    discoveries: Vec<Discovery>,
+    /// The linked worktrees found while traversing, along with their path as per [host_path], left
+    /// to their repository if it lists them.
+    crawled_worktrees: Vec<(PathBuf, Discovery)>,
+    /// The path of the linked worktrees listed by their repository, as per [host_path].
+    listed_worktrees: HashSet<PathBuf>,
+    /// How often to yield a [Discovery::Tick], if at all.
+    tick_interval: Option<Duration>,
+    last_tick: Instant,
+}
+
+/// A directory left to inspect, along with its children and depth.
+struct PendingDir {
+    path: PathBuf,
+    children: Vec<Child>,
+    /// The exclusion rules applying to the directory, i.e. those of its parent.
+    scope: ExcludeScope,
+    depth: usize,
+    /// Whether the directory was reached through a symbolic link, so that its path is not its real
+    /// path.
+    via_symlink: bool,
+}
+
+impl Iterator for RepositoryIter {
+    type Item = Discovery;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(discovery) = self.discoveries.pop() {
+            return Some(discovery);
+        }
+        while let Some(PendingDir {
+            path: parent,
+            children,
+            scope,
+            depth,
+            via_symlink,
+        }) = self.next_dir()
+        {
+            let entries = children
+                .iter()
+                .filter_map(|child| {
+                    Some(Entry {
+                        name: child.path.file_name()?,
+                        is_dir: child.is_dir,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let marker = self.markers.find(&entries);
+            if let Some(marker) = marker {
+                let Ok(path) = parent.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let host_path = self.host_path(match via_symlink {
+                    true => real_path(&parent).unwrap_or_else(|_| parent.clone()),
+                    false => parent.clone(),
+                });
+                let discovery = match via_symlink {
+                    true => Discovery::Labelled {
+                        label: path.to_path_buf(),
+                        path: host_path.clone(),
+                        marker,
+                    },
+                    false => Discovery::Repository(path.to_path_buf(), marker),
+                };
+                if !self.descend {
+                    return Some(discovery);
+                }
+                if is_linked_worktree(&parent, &entries) {
+                    self.crawled_worktrees.push((host_path, discovery));
+                } else {
+                    self.discoveries = self.worktrees(path, &parent, marker);
+                    self.discoveries.push(discovery);
+                }
+            }
+            if depth < self.max_depth && marker != Some(MarkerKind::GitBare) {
+                let scope = scope.enter(&parent, &entries);
+                for child in children.iter().filter(|child| child.is_dir) {
+                    let entry = Entry {
+                        name: child.path.file_name().unwrap_or_default(),
+                        is_dir: true,
+                    };
+                    if scope.is_excluded(&child.path)
+                        || (marker.is_some() && self.markers.is_vcs_metadata(&entry))
+                    {
+                        continue;
+                    }
+                    // Links are recorded as visited once inspected, see [RepositoryIter::next_dir].
+                    if let (Some(visited), false) = (&self.visited, child.is_symlink) {
+                        if !visited.insert(&child.path) {
+                            continue;
+                        }
+                    }
+                    let children =
+                        get_children(&child.path, self.visited.is_some()).unwrap_or_default();
+                    if !children.is_empty() {
+                        let pending = PendingDir {
+                            path: child.path.clone(),
+                            children,
+                            scope: scope.clone(),
+                            depth: depth + 1,
+                            via_symlink: via_symlink || child.is_symlink,
+                        };
+                        match child.is_symlink {
+                            true => self.links_to_walk.push(pending),
+                            false => self.dirs_to_walk.push(pending),
+                        }
+                    }
+                }
+            }
+            if let Some(discovery) = self.discoveries.pop() {
+                return Some(discovery);
+            }
+            if self
+                .tick_interval
+                .is_some_and(|interval| self.last_tick.elapsed() >= interval)
             {
-                repositories.insert(parent);
-                continue 'outer;
+                self.last_tick = Instant::now();
+                return Some(Discovery::Tick);
             }
         }
-        if depth < max_depth {
-            for dir in children {
-                let child_dirs = get_child_directories(&dir);
-                if !child_dirs.is_empty() {
-                    dirs_to_walk.push((dir, child_dirs, depth + 1));
-                }
+
+        let crawled_worktrees = std::mem::take(&mut self.crawled_worktrees);
+        self.discoveries = crawled_worktrees
+            .into_iter()
+            .filter(|(path, _)| !self.listed_worktrees.contains(path))
+            .map(|(_, discovery)| discovery)
+            .collect();
+        self.discoveries.pop()
+    }
+}
+
+impl RepositoryIter {
+    /// Yields a [Discovery::Tick] every `interval` while inspecting directories, even if no
+    /// project is found.
+    pub(crate) fn with_tick_interval(mut self, interval: Duration) -> Self {
+        self.tick_interval = Some(interval);
+        self
+    }
+
+    /// Pops the next directory to inspect. Symbolic links come last, and are skipped if their
+    /// target was already inspected.
+    fn next_dir(&mut self) -> Option<PendingDir> {
+        if let Some(dir) = self.dirs_to_walk.pop() {
+            return Some(dir);
+        }
+        while let Some(link) = self.links_to_walk.pop() {
+            if self
+                .visited
+                .as_ref()
+                .is_some_and(|visited| visited.insert(&link.path))
+            {
+                return Some(link);
             }
         }
+        None
+    }
+
+    /// Lists the linked worktrees of the repository at `path`, labelled `label`, and records them
+    /// as listed. Worktrees whose directory no longer exists are skipped, as far as the plugin can
+    /// see.
+    fn worktrees(&mut self, label: &Path, path: &Path, marker: MarkerKind) -> Vec<Discovery> {
+        linked_worktrees(path, marker)
+            .into_iter()
+            .filter_map(|worktree| {
+                // Git records absolute paths on the host, unless configured to record relative
+                // paths, which are resolved within the plugin's filesystem.
+                if worktree.path.starts_with("/host") && !worktree.path.is_dir() {
+                    return None;
+                }
+                let mut worktree_label = label.as_os_str().to_owned();
+                worktree_label.push("@");
+                worktree_label.push(&worktree.name);
+                let path = self.host_path(worktree.path);
+                self.listed_worktrees.insert(path.clone());
+                Some(Discovery::Labelled {
+                    label: worktree_label.into(),
+                    path,
+                    marker: MarkerKind::GitWorktree,
+                })
+            })
+            .collect()
     }
 
-    repositories
-        .iter()
-        .filter_map(|path| path.strip_prefix(root).ok())
-        .map(|p| p.to_path_buf())
-        .collect::<BTreeSet<_>>()
+    /// Translates `path`, either from the plugin's filesystem where the plugin's CWD is mounted
+    /// under `/host`, or from the host, to a path relative to the plugin's CWD. Paths outside of
+    /// the plugin's CWD are left absolute on the host.
+    fn host_path(&self, path: PathBuf) -> PathBuf {
+        match path
+            .strip_prefix("/host")
+            .or_else(|_| path.strip_prefix(&self.host_cwd))
+        {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path,
+        }
+    }
 }
 
-fn get_child_directories(path: &PathBuf) -> Vec<PathBuf> {
+/// Lists the entries of the directory at `path`. Symbolic links to directories count as
+/// directories if [follow_symlinks] is set.
+fn get_children(path: &PathBuf, follow_symlinks: bool) -> io::Result<Vec<Child>> {
     let mut children = Vec::new();
 
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                if let Ok(ft) = entry.file_type() {
-                    if ft.is_dir() {
-                        children.push(entry.path());
-                    }
-                }
+    for entry in fs::read_dir(path)? {
+        if let Ok(entry) = entry {
+            if let Ok(ft) = entry.file_type() {
+                let path = entry.path();
+                let is_dir = match ft.is_symlink() && follow_symlinks {
+                    true => fs::metadata(&path).is_ok_and(|metadata| metadata.is_dir()),
+                    false => ft.is_dir(),
+                };
+                children.push(Child {
+                    path,
+                    is_dir,
+                    is_symlink: ft.is_symlink(),
+                });
             }
         }
     }
 
-    children
+    Ok(children)
 }
@@ -1,21 +1,76 @@
-use std::{collections::BTreeSet, path::PathBuf};
+use std::{collections::BTreeMap, path::PathBuf};
 
+use project_markers::{ExcludeSet, MarkerKind, MarkerSet};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub(crate) enum FileSystemWorkerMessage {
+    /// Sent by the plugin to request a crawl.
     Crawl,
+    /// Sent by the worker for each batch of repositories found while crawling.
+    CrawlBatch,
+    /// Sent by the worker once the crawl is complete. No batch follows this message.
+    CrawlComplete,
+    /// Sent by the worker instead of `CrawlComplete` if the crawl failed, e.g. because the root
+    /// cannot be read. No batch follows this message.
+    CrawlFailed,
 }
 
 /// Request associated with a `FileSystemWorkerMessage::Crawl`.
 #[derive(Serialize, Deserialize)]
 pub(crate) struct RepositoryCrawlerRequest {
+    /// The directory to scan, relative to the plugin's CWD.
     pub root: PathBuf,
     pub max_depth: usize,
+    /// The markers identifying project roots.
+    pub markers: MarkerSet,
+    /// The directories to skip.
+    pub excludes: ExcludeSet,
+    /// Whether to keep crawling below project roots, and list the linked worktrees of Git
+    /// repositories.
+    pub descend: bool,
+    /// Whether to crawl symbolic links to directories.
+    pub follow_symlinks: bool,
+    /// The plugin's CWD on the host, to relate the host paths recorded by Git to the directories
+    /// mounted under `/host`.
+    pub host_cwd: PathBuf,
 }
 
-/// Response from a worker to a `FileSystemWorkerMessage::Crawl`.
+/// Partial response from a worker to a `FileSystemWorkerMessage::Crawl`, associated with a
+/// `FileSystemWorkerMessage::CrawlBatch`.
 #[derive(Serialize, Deserialize)]
 pub(crate) struct RepositoryCrawlerResponse {
-    pub repositories: BTreeSet<PathBuf>,
+    /// The root of the request this is a response to, so that results from concurrent scans can be
+    /// told apart.
+    pub root: PathBuf,
+    /// The repositories found under `root` since the previous batch, relative to `root`, along with
+    /// the kind of marker that identified them.
+    pub repositories: BTreeMap<PathBuf, MarkerKind>,
+    /// The projects found since the previous batch whose path differs from their label, i.e.
+    /// linked worktrees (labelled `<repository>@<worktree>`) and projects reached through symbolic
+    /// links (labelled with the path of the link), by label relative to `root`, along with their
+    /// path and the kind of marker that identified them. Their path is relative to the plugin's
+    /// CWD if they live under it, and absolute on the host otherwise.
+    pub labelled: BTreeMap<PathBuf, (PathBuf, MarkerKind)>,
+}
+
+/// Final response from a worker to a `FileSystemWorkerMessage::Crawl`, associated with a
+/// `FileSystemWorkerMessage::CrawlComplete`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RepositoryCrawlerCompletion {
+    /// The root of the request this is a response to.
+    pub root: PathBuf,
+    /// The total number of repositories found under `root` across all batches, including linked
+    /// worktrees.
+    pub repository_count: usize,
+}
+
+/// Final response from a worker to a `FileSystemWorkerMessage::Crawl` that failed, associated with
+/// a `FileSystemWorkerMessage::CrawlFailed`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RepositoryCrawlerFailure {
+    /// The root of the request this is a response to.
+    pub root: PathBuf,
+    /// Why the crawl failed.
+    pub reason: String,
 }